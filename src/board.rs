@@ -1,4 +1,11 @@
+use crate::cells::BoundingBox;
+use crate::emissions::Emission;
+use crate::growth::LinearGrowth;
+use crate::novelty::Behavior;
+use crate::{Census, Stats};
+
 /// Before it's solved and before it's saved
+#[derive(Debug, Clone)]
 pub struct Initial {
     /// size of the whole board
     pub size: u32,
@@ -13,9 +20,22 @@ pub struct Initial {
     /// How many of the subdivisions the initial cells are
     /// placed into
     pub starting_subdiv_utilization: usize,
+
+    /// The part of the board the subdivisions are laid out over. Only random and reseeded boards
+    /// are seeded there directly, but it's passed down so it can evolve. The same goes for the
+    /// subdivisions above, which bred boards carry without using.
+    pub seed_region: BoundingBox,
+}
+
+impl Initial {
+    /// The smallest box around the initial cells, None if there aren't any
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::around(self.cells.iter())
+    }
 }
 
 /// After it's solved but still before it's saved
+#[derive(Debug, Clone)]
 pub struct Solved {
     pub initial: Initial,
 
@@ -25,9 +45,92 @@ pub struct Solved {
     /// If the board ended up looping, how many unique iterations existed within that loop
     /// before it repeated
     pub period: Option<usize>,
+
+    /// If the board ended up looping, the first generation of the loop. Everything before it is
+    /// the board settling down, so this is how long it took to stabilize.
+    pub cycle_start: Option<usize>,
+
+    /// The objects the board ended up as, if it settled into a loop
+    pub census: Census,
+
+    /// How the board changed over every generation, if that was recorded. These aren't loaded
+    /// along with boards from the db, see Db::load_stats.
+    pub stats: Option<Stats>,
+
+    /// Spaceships that were seen breaking away from the board, if they were being looked for
+    pub emissions: Vec<Emission>,
+
+    /// If the board never repeated b/c it kept growing at a steady rate, like a gun or puffer
+    pub growth: Option<LinearGrowth>,
+
+    /// How many cells were alive when the board stopped
+    pub final_population: usize,
+
+    /// The most cells that were alive at any one time
+    pub peak_population: usize,
+
+    /// The box around the living cells when the board stopped, None if they'd all died
+    pub final_bounding_box: Option<BoundingBox>,
+
+    /// The spec of the fitness function the board was evolved under, see fitness::Weighted
+    pub fitness: String,
+
+    /// The run of evolution that made the board, None for boards from before runs were recorded
+    pub run_id: Option<i64>,
+
+    /// If the board came back around to its own initial state, whether it did so in place or
+    /// somewhere else on the board
+    pub reproduces_as: Option<Reproduction>,
+
+    /// How close the board came to its own initial state, if that was being looked for, see
+    /// Snapshot::origin_similarity
+    pub origin_similarity: Option<f64>,
+
+    /// How the board played out, if novelty was being looked for, see novelty::Behavior
+    pub behavior: Option<Behavior>,
+
+    /// How different the board's behavior was from what had been seen when it was solved
+    pub novelty: Option<f64>,
+
+    /// The island the board lives on, None if it was evolved in a shared population, see islands
+    pub island: Option<u32>,
+}
+
+/// What a board that comes back to its own initial state turned out to be. Oscillators have to
+/// have a period of at least 3, since still lifes and period 2 ones like the blinker, toad and
+/// beacon come back too easily to count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reproduction {
+    /// Back in the same spot
+    Oscillator,
+
+    /// Back, but moved over
+    Spaceship,
+}
+
+impl Reproduction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reproduction::Oscillator => "oscillator",
+            Reproduction::Spaceship => "spaceship",
+        }
+    }
+}
+
+impl std::str::FromStr for Reproduction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oscillator" => Ok(Reproduction::Oscillator),
+            "spaceship" => Ok(Reproduction::Spaceship),
+            _ => Err(format!("unknown reproduction {}", s)),
+        }
+    }
 }
 
 /// From the DB, it's been solved and saved
+#[derive(Debug, Clone)]
 pub struct Saved {
     pub solved: Solved,
 
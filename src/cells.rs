@@ -9,6 +9,63 @@ pub struct Cells {
     size: u32,
 }
 
+/// Which cells count as touching when grouping living cells into clusters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only above, below, left and right
+    Orthogonal,
+
+    /// All 8 surrounding cells, diagonals included
+    Moore,
+}
+
+/// A group of living cells close enough together to be thought of as a single thing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    /// The cluster's cells where they are on the board, in order
+    pub cells: Vec<(u32, u32)>,
+
+    /// The cluster's cells relative to the top left of its bounding box, in order. A cluster that
+    /// wraps around the edge of the board comes out in one piece here.
+    pub pattern: Vec<(u32, u32)>,
+
+    pub bounding_box: BoundingBox,
+}
+
+/// The smallest box around a cluster. It can hang off the bottom or right edge of the board, in
+/// which case the rest of it is wrapped around to the top or left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub top: u32,
+    pub left: u32,
+    pub height: u32,
+    pub width: u32,
+}
+
+impl BoundingBox {
+    /// The smallest box around the given cells, without taking wrapping into account. None if
+    /// there aren't any.
+    pub fn around<'a>(cells: impl Iterator<Item = &'a (u32, u32)> + Clone) -> Option<BoundingBox> {
+        let top = cells.clone().map(|(i, _)| *i).min()?;
+        let bottom = cells.clone().map(|(i, _)| *i).max()?;
+        let left = cells.clone().map(|(_, j)| *j).min()?;
+        let right = cells.map(|(_, j)| *j).max()?;
+
+        Some(BoundingBox {
+            top,
+            left,
+            height: bottom - top + 1,
+            width: right - left + 1,
+        })
+    }
+}
+
+impl Cluster {
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+}
+
 impl Cells {
     pub fn new(size: u32) -> Cells {
         Cells {
@@ -42,6 +99,18 @@ impl Cells {
         self.uncommitted_cells.remove(&(i, j));
     }
 
+    /// Take a cell off the board right away, rather than as part of the next commit. This is for
+    /// reaching in between steps, like to clear away a spaceship that's flown off.
+    pub fn remove(&mut self, i: u32, j: u32) {
+        self.extent_cells.remove(&(i, j));
+        self.uncommitted_cells.remove(&(i, j));
+    }
+
+    /// The size the board was made with. Coordinates go from 0 through this, inclusive.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
     /// Is the given coord a living cell (as opposed to an empty or dead one)?
     pub fn is_alive(&self, i: u32, j: u32) -> bool {
         self.extent_cells.contains_key(&(i, j))
@@ -49,7 +118,7 @@ impl Cells {
 
     /// Returns the age of the cell, or 0 if it's not alive
     pub fn get_age(&self, i: u32, j: u32) -> usize {
-        *self.extent_cells.get(&(i, j)).unwrap_or(&(0 as usize))
+        *self.extent_cells.get(&(i, j)).unwrap_or(&0)
     }
 
     /// How many living cells are there?
@@ -76,7 +145,7 @@ impl Cells {
     pub fn living_cells(&self) -> Vec<(u32, u32)> {
         // TODO Is it dangerous to give away dereferenced locations like this?
         // Whoever gets it, if they modify it, aren't they changing our data?
-        self.extent_cells.keys().map(|key| *key).collect()
+        self.extent_cells.keys().copied().collect()
     }
 
     /// Get a list of all the living cells and their neighbors, living or not
@@ -128,6 +197,90 @@ impl Cells {
         ]
     }
 
+    /// The smallest box around every living cell, by board coordinates, without taking wrapping
+    /// into account. None if nothing's alive.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        BoundingBox::around(self.extent_cells.keys())
+    }
+
+    /// Split the living cells up into clusters. Two cells are in the same cluster if they're within
+    /// merge_distance of each other, counting steps the way the connectivity allows (so diagonal
+    /// steps are free with Moore connectivity). A merge_distance of 1 groups cells that touch;
+    /// anything bigger also joins up islands that are near each other. Clusters come back in order
+    /// of their first cell.
+    pub fn clusters(&self, connectivity: Connectivity, merge_distance: u32) -> Vec<Cluster> {
+        let distance = merge_distance.max(1) as i64;
+        let width = self.size as i64 + 1;
+
+        let reach: Vec<(i64, i64)> = (-distance..=distance)
+            .flat_map(|di| (-distance..=distance).map(move |dj| (di, dj)))
+            .filter(|(di, dj)| (*di, *dj) != (0, 0))
+            .filter(|(di, dj)| match connectivity {
+                Connectivity::Orthogonal => di.abs() + dj.abs() <= distance,
+                Connectivity::Moore => true,
+            })
+            .collect();
+
+        let mut living = self.living_cells();
+        living.sort_unstable();
+
+        let mut visited = HashSet::new();
+        let mut clusters = vec![];
+
+        for start in living {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            // Alongside each cell, keep where it is relative to the first one, so we don't lose
+            // track of which side of the board's edge it's on
+            let mut found = vec![];
+            let mut frontier = vec![(start, (0, 0))];
+
+            while let Some(((i, j), (ri, rj))) = frontier.pop() {
+                found.push(((i, j), (ri, rj)));
+
+                for (di, dj) in &reach {
+                    let neighbor = (
+                        (i as i64 + di).rem_euclid(width) as u32,
+                        (j as i64 + dj).rem_euclid(width) as u32,
+                    );
+
+                    if self.is_alive(neighbor.0, neighbor.1) && visited.insert(neighbor) {
+                        frontier.push((neighbor, (ri + di, rj + dj)));
+                    }
+                }
+            }
+
+            let min_i = found.iter().map(|(_, (ri, _))| *ri).min().unwrap();
+            let min_j = found.iter().map(|(_, (_, rj))| *rj).min().unwrap();
+            let max_i = found.iter().map(|(_, (ri, _))| *ri).max().unwrap();
+            let max_j = found.iter().map(|(_, (_, rj))| *rj).max().unwrap();
+
+            let mut cells: Vec<(u32, u32)> = found.iter().map(|(cell, _)| *cell).collect();
+            cells.sort_unstable();
+
+            let mut pattern: Vec<(u32, u32)> = found
+                .iter()
+                .map(|(_, (ri, rj))| ((ri - min_i) as u32, (rj - min_j) as u32))
+                .collect();
+            pattern.sort_unstable();
+
+            clusters.push(Cluster {
+                cells,
+                pattern,
+                bounding_box: BoundingBox {
+                    top: (start.0 as i64 + min_i).rem_euclid(width) as u32,
+                    left: (start.1 as i64 + min_j).rem_euclid(width) as u32,
+                    height: (max_i - min_i + 1) as u32,
+                    width: (max_j - min_j + 1) as u32,
+                },
+            });
+        }
+
+        clusters
+    }
+
     /// When you perform mutable operations that add/remove cells from the grid,
     /// they need to be stored in a secondary location. This is b/c if you add a cell
     /// to say grid 1, then check grid 2 for the rules, it'll include grid 1. This is
@@ -146,3 +299,23 @@ impl Cells {
         });
     }
 }
+
+#[test]
+fn clusters_merge_nearby_islands() {
+    let mut cells = Cells::new(20);
+
+    // Two blocks with a one cell gap between them, the second hanging over the board's edge
+    cells.birth_multiple(&[(5, 17), (5, 18), (6, 17), (6, 18)]);
+    cells.birth_multiple(&[(5, 20), (5, 0), (6, 20), (6, 0)]);
+    cells.commit();
+
+    let separate = cells.clusters(Connectivity::Moore, 1);
+    assert_eq!(separate.len(), 2);
+    assert_eq!(separate[0].bounding_box, BoundingBox { top: 5, left: 20, height: 2, width: 2 });
+    assert_eq!(separate[0].pattern, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+    let merged = cells.clusters(Connectivity::Moore, 2);
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].population(), 8);
+    assert_eq!(merged[0].bounding_box.width, 5);
+}
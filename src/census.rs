@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::snapshot::Velocity;
+use crate::cells::Connectivity;
+use crate::{Cells, Game, Snapshot};
+
+// The census's responsibility is to:
+// * Split a board's living cells up into separate objects
+// * Figure out what each of those objects is, by running it on its own board until it repeats
+// * Name it the way apgsearch does, so our results line up with everyone else's catalogs
+//
+// apgcodes look like xs4_33 (a still life of 4 cells), xp2_7 (a period 2 oscillator) or xq4_153 (a
+// period 4 spaceship). The part after the underscore is the object in "extended Wechsler format",
+// which encodes the pattern 5 rows at a time, one character per column. An object can be drawn
+// many ways (every phase, rotated and flipped), so the shortest, then alphabetically first,
+// encoding is the one that counts.
+
+/// Objects that haven't settled into a loop within this many generations are left unclassified
+const MAX_GENERATIONS: usize = 512;
+
+/// How much empty space to leave around an object when running it on its own board, so it has
+/// room to move and its sparks don't wrap around into itself
+const MARGIN: u32 = 8;
+
+/// What's left of a board once it's settled down, as counts of each kind of object
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Census {
+    /// apgcode => how many of them there are
+    pub objects: BTreeMap<String, usize>,
+}
+
+/// A single object, classified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub apgcode: String,
+
+    /// 1 for still lifes
+    pub period: usize,
+
+    /// How far it moves over its period, stationary for still lifes and oscillators
+    pub velocity: Velocity,
+}
+
+impl Object {
+    pub fn is_spaceship(&self) -> bool {
+        !self.velocity.is_stationary()
+    }
+}
+
+impl Census {
+    /// Split the living cells into objects and count up what they are. Anything that doesn't
+    /// behave on its own like it does on the board (say it was only stable b/c of its neighbors)
+    /// gets counted as PATHOLOGICAL.
+    pub fn take(cells: &Cells) -> Census {
+        let mut objects = BTreeMap::new();
+
+        // Boards tend to be covered in lots of the same few things, so only run each shape once
+        let mut classified: HashMap<Vec<(u32, u32)>, Option<Object>> = HashMap::new();
+
+        // Cells that touch, diagonals included, make up an object
+        for cluster in cells.clusters(Connectivity::Moore, 1) {
+            let object = classified
+                .entry(cluster.pattern)
+                .or_insert_with_key(|pattern| classify(pattern));
+
+            let apgcode = match object {
+                Some(object) => object.apgcode.clone(),
+                None => String::from("PATHOLOGICAL"),
+            };
+
+            *objects.entry(apgcode).or_insert(0) += 1;
+        }
+
+        Census { objects }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// Lists the most common objects first, like "3 block (xs4_33), 1 blinker (xp2_7)"
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut objects: Vec<(&String, &usize)> = self.objects.iter().collect();
+        objects.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        let described: Vec<String> = objects
+            .iter()
+            .map(|(apgcode, count)| match name(apgcode) {
+                Some(name) => format!("{} {} ({})", count, name, apgcode),
+                None => format!("{} {}", count, apgcode),
+            })
+            .collect();
+
+        write!(f, "{}", described.join(", "))
+    }
+}
+
+/// Figure out what a single object is. The cells should all belong to the one object, and can be
+/// anywhere, they're shifted onto a fresh board first. Returns None if it doesn't settle into a
+/// loop that starts with the cells it was given.
+pub fn classify(pattern: &[(u32, u32)]) -> Option<Object> {
+    let pattern: Vec<(i64, i64)> = pattern.iter().map(|(i, j)| (*i as i64, *j as i64)).collect();
+    let pattern = normalize(&pattern);
+
+    let height = pattern.iter().map(|(i, _)| *i).max()? as u32 + 1;
+    let width = pattern.iter().map(|(_, j)| *j).max()? as u32 + 1;
+    let size = height.max(width) + MARGIN * 2;
+
+    let new_game = |snapshot: Option<Snapshot>| {
+        let placed: Vec<(u32, u32)> = pattern
+            .iter()
+            .map(|(i, j)| (*i as u32 + MARGIN, *j as u32 + MARGIN))
+            .collect();
+
+        let mut cells = Cells::new(size);
+        cells.birth_multiple(&placed);
+        cells.commit();
+        Game::new(snapshot, cells, None, None)
+    };
+
+    let mut game = new_game(Some(Snapshot::new(size)));
+
+    while !game.snapshot.as_ref().unwrap().has_repeat() {
+        if game.iterations >= MAX_GENERATIONS {
+            return None;
+        }
+
+        game.step();
+    }
+
+    let snapshot = game.snapshot.unwrap();
+
+    // If it turned into something else before it started looping, it's not an object by itself
+    if snapshot.cycle_start() != Some(0) {
+        return None;
+    }
+
+    let period = snapshot.period()?;
+    let velocity = snapshot.velocity()?;
+
+    // Every phase is a candidate for the canonical drawing
+    let mut phases = vec![];
+    let mut game = new_game(None);
+
+    for _ in 0..period {
+        let living: Vec<(i64, i64)> = game
+            .cells
+            .living_cells()
+            .iter()
+            .map(|(i, j)| (*i as i64, *j as i64))
+            .collect();
+        phases.push(living);
+        game.step();
+    }
+
+    let prefix = if !velocity.is_stationary() {
+        format!("xq{}", period)
+    } else if period == 1 {
+        format!("xs{}", pattern.len())
+    } else {
+        format!("xp{}", period)
+    };
+
+    Some(Object {
+        apgcode: format!("{}_{}", prefix, canonical_wechsler(&phases)),
+        period,
+        velocity,
+    })
+}
+
+/// The common name of an object, for the ones that have one we know
+pub fn name(apgcode: &str) -> Option<&'static str> {
+    let name = match apgcode {
+        "xs4_33" => "block",
+        "xs6_696" => "beehive",
+        "xs7_2596" => "loaf",
+        "xs5_253" => "boat",
+        "xs6_356" => "ship",
+        "xs4_252" => "tub",
+        "xs8_6996" => "pond",
+        "xp2_7" => "blinker",
+        "xp2_7e" => "toad",
+        "xp2_318c" => "beacon",
+        "xq4_153" => "glider",
+        "xq4_6frc" => "lightweight spaceship",
+        "xq4_27dee6" => "middleweight spaceship",
+        "xq4_27deee6" => "heavyweight spaceship",
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Shift the cells up against the origin and put them in order, so the same shape always comes
+/// out the same
+fn normalize(cells: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let min_i = cells.iter().map(|(i, _)| *i).min().unwrap_or(0);
+    let min_j = cells.iter().map(|(_, j)| *j).min().unwrap_or(0);
+
+    let mut normalized: Vec<(i64, i64)> =
+        cells.iter().map(|(i, j)| (i - min_i, j - min_j)).collect();
+    normalized.sort_unstable();
+    normalized
+}
+
+/// The shortest, then alphabetically first, encoding across every phase and orientation
+fn canonical_wechsler(phases: &[Vec<(i64, i64)>]) -> String {
+    phases
+        .iter()
+        .flat_map(|phase| {
+            crate::snapshot::Symmetry::ALL.iter().map(move |symmetry| {
+                let transformed: Vec<(i64, i64)> =
+                    phase.iter().map(|cell| symmetry.apply(*cell)).collect();
+                wechsler(&normalize(&transformed))
+            })
+        })
+        .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+        .unwrap_or_default()
+}
+
+/// Extended Wechsler format. Rows are taken 5 at a time as strips, and each column of a strip
+/// becomes one character (the 5 cells read as bits, lowest row first). Strips are separated by z,
+/// trailing empty columns are dropped, and runs of empty columns are shortened with w, x and y.
+fn wechsler(cells: &[(i64, i64)]) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    let height = cells.iter().map(|(i, _)| *i).max().map_or(0, |i| i + 1);
+    let width = cells.iter().map(|(_, j)| *j).max().map_or(0, |j| j + 1);
+    let cells: HashSet<&(i64, i64)> = cells.iter().collect();
+
+    let strips: Vec<String> = (0..(height + 4) / 5)
+        .map(|strip| {
+            let columns: Vec<usize> = (0..width)
+                .map(|j| {
+                    (0..5)
+                        .filter(|bit| cells.contains(&(strip * 5 + bit, j)))
+                        .map(|bit| 1 << bit)
+                        .sum()
+                })
+                .collect();
+
+            let mut encoded = String::new();
+            let mut zeros = 0;
+
+            for column in columns {
+                if column == 0 {
+                    zeros += 1;
+                } else {
+                    push_zeros(&mut encoded, zeros);
+                    zeros = 0;
+                    encoded.push(DIGITS[column] as char);
+                }
+            }
+
+            // Whatever zeros are left over are at the end of the strip, and get dropped
+            encoded
+        })
+        .collect();
+
+    strips.join("z")
+}
+
+/// Runs of empty columns within a strip: 0, w, x, then y followed by how many past 4
+fn push_zeros(encoded: &mut String, mut zeros: usize) {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+    while zeros > 0 {
+        let run = zeros.min(39);
+
+        match run {
+            1 => encoded.push('0'),
+            2 => encoded.push('w'),
+            3 => encoded.push('x'),
+            _ => {
+                encoded.push('y');
+                encoded.push(DIGITS[run - 4] as char);
+            }
+        }
+
+        zeros -= run;
+    }
+}
+
+#[test]
+fn classifies_common_objects() {
+    let cases: [(&[(u32, u32)], &str); 6] = [
+        (&[(0, 0), (0, 1), (1, 0), (1, 1)], "xs4_33"),
+        (&[(0, 1), (0, 2), (1, 0), (1, 3), (2, 1), (2, 2)], "xs6_696"),
+        (&[(0, 0), (0, 1), (0, 2)], "xp2_7"),
+        (&[(0, 1), (0, 2), (0, 3), (1, 0), (1, 1), (1, 2)], "xp2_7e"),
+        (&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)], "xq4_153"),
+        (
+            &[(0, 1), (0, 4), (1, 0), (2, 0), (2, 4), (3, 0), (3, 1), (3, 2), (3, 3)],
+            "xq4_6frc",
+        ),
+    ];
+
+    for (pattern, apgcode) in cases {
+        assert_eq!(classify(pattern).unwrap().apgcode, apgcode);
+    }
+}
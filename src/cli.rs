@@ -1,5 +1,7 @@
 use clap::{Parser, Subcommand};
 
+use crate::{evolver, fitness, genome, islands, map_elites, optimizer, selection, snapshot};
+
 /// An evolutionary solver to conway's game of life, in color!
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -17,8 +19,106 @@ pub enum Commands {
     /// Run the program in evolve mode
     Evolve {
         /// How many threads to use in evolve mode
-        #[arg(short, long, default_value_t = 4)]
+        #[arg(
+            short,
+            long,
+            default_value_t = 4,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+        )]
         threads: usize,
+
+        /// Also treat rotated and reflected states as repeats when looking for loops
+        #[arg(long)]
+        symmetry: bool,
+
+        /// How to look for loops. Brent uses constant memory, which helps on very long runs.
+        #[arg(long, value_enum, default_value_t = snapshot::Strategy::Full)]
+        cycle_detection: snapshot::Strategy,
+
+        /// Record population, births, deaths, bounding box and mean age for every generation of
+        /// each board, and save them with the board
+        #[arg(long)]
+        record_stats: bool,
+
+        /// Look for gliders and other spaceships breaking away from each board
+        #[arg(long)]
+        detect_emissions: bool,
+
+        /// Take spaceships off the board once they've broken away, so they don't wrap around and
+        /// crash back into it. Implies --detect-emissions.
+        #[arg(long)]
+        remove_emissions: bool,
+
+        /// What kind of board to look for
+        #[arg(long, value_enum, default_value_t = evolver::Mode::Standard)]
+        mode: evolver::Mode,
+
+        /// What to evolve for, as a weighted sum of objectives like "longevity=1,period=20". The
+        /// objectives are longevity, initial-cells, period, stabilization, final-population,
+        /// peak-population, census-diversity, bounding-box-growth, spaceship-found,
+        /// self-reproduction and novelty, and a - in front of one counts against it. In pareto
+        /// mode the objectives are kept apart rather than added up. Boards are only compared
+        /// against boards evolved for the same thing. Defaults to what the mode is looking for,
+        /// which in standard mode is longevity=1,period=20.
+        #[arg(long)]
+        fitness: Option<fitness::Weighted>,
+
+        /// How parents' cells get combined when breeding a new board
+        #[arg(long, value_enum, default_value_t = genome::Crossover::RegionSwap)]
+        crossover: genome::Crossover,
+
+        /// How parents are picked: tournament=K takes the fittest of K at random, roulette picks
+        /// in proportion to fitness, rank in proportion to rank, and truncation=F uniformly from
+        /// the fittest fraction F
+        #[arg(long, default_value = "tournament=3")]
+        selection: selection::Strategy,
+
+        /// How many boards to keep around
+        #[arg(long, default_value_t = 10)]
+        population_size: usize,
+
+        /// How many of the fittest boards are never replaced. Has to be less than the population
+        /// size.
+        #[arg(long, default_value_t = 0)]
+        elites: usize,
+
+        /// How new boards make their way into a full population
+        #[arg(long, value_enum, default_value_t = evolver::Replacement::ReplaceWorst)]
+        replacement: evolver::Replacement,
+
+        /// Seed for all of the run's randomness, picked at random if not given. Either way it's
+        /// saved with the run. A single threaded run with the same seed, on the same database,
+        /// makes the same boards.
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// In map-elites mode, the two objectives boards are laid out on the grid by, across then
+        /// down
+        #[arg(long, default_value = "initial-cells,period")]
+        descriptors: map_elites::Descriptors,
+
+        /// Give each thread a population of its own, an island, rather than all of them sharing
+        /// one. Every so often each island sends its fittest boards off to its neighbors.
+        #[arg(long)]
+        islands: bool,
+
+        /// With --islands, how many boards each island evolves between sending off migrants
+        #[arg(long, default_value_t = 50)]
+        migration_interval: usize,
+
+        /// With --islands, how many of an island's fittest boards go out each time
+        #[arg(long, default_value_t = 1)]
+        migrants: usize,
+
+        /// With --islands, which islands migrants go to
+        #[arg(long, value_enum, default_value_t = islands::Topology::Ring)]
+        topology: islands::Topology,
+
+        /// With --islands, what each island evolves for, like --fitness. Give it once per island,
+        /// in order; islands past the last one given start over from the first. Every island
+        /// evolves for --fitness if it isn't given.
+        #[arg(long)]
+        island_fitness: Vec<fitness::Weighted>,
     },
 
     /// Display one of the evolved boards
@@ -26,7 +126,72 @@ pub enum Commands {
         /// How long (in ms) to wait between each board iteration
         #[arg(short, long, default_value_t = 5)]
         delay: usize,
-    }
+    },
+
+    /// Polish one of the evolved boards by flipping a single cell at a time and keeping the
+    /// changes that help. The best board found is saved as a new board, if it beats the old one.
+    Optimize {
+        /// The id of the board, as shown in the display listing
+        id: i64,
+
+        /// Whether to only ever keep changes that don't hurt, or to sometimes take a worse board
+        /// early on to get out of a rut
+        #[arg(long, value_enum, default_value_t = optimizer::Method::HillClimbing)]
+        method: optimizer::Method,
+
+        /// How many single cell changes to try
+        #[arg(long, default_value_t = 1000)]
+        steps: usize,
+
+        /// What to optimize for, like evolve's --fitness. Defaults to what the board was evolved
+        /// for.
+        #[arg(long)]
+        fitness: Option<fitness::Weighted>,
+
+        /// With --method annealing, roughly how much fitness it's willing to give up at the start
+        #[arg(long, default_value_t = 100.0)]
+        temperature: f64,
+
+        /// With --method annealing, what the temperature's multiplied by after every step
+        #[arg(long, default_value_t = 0.995)]
+        cooling: f64,
+
+        /// Seed for the optimizer's randomness, picked at random if not given
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Run every pattern that fits in a small box, once for each shape, and save the ones that
+    /// stand out. There are 2^(width*height) ways to fill the box, so this is for boxes of 25
+    /// cells or fewer, where it shows what evolution should be able to find. Replaces whatever an
+    /// earlier search of the same box saved.
+    Search {
+        /// How many cells across the box is
+        width: u32,
+
+        /// How many cells down the box is
+        height: u32,
+
+        /// How many of the longest lived patterns to save
+        #[arg(long, default_value_t = 10)]
+        longest: usize,
+
+        /// Save every oscillator of this period. Can be given more than once. Period 1 is still
+        /// lifes.
+        #[arg(long)]
+        period: Vec<usize>,
+
+        /// Save every pattern that makes or turns into a spaceship
+        #[arg(long)]
+        spaceships: bool,
+    },
+
+    /// Print the per generation stats of one of the boards as CSV, for plotting. Boards that
+    /// weren't evolved with --record-stats are run again to get them.
+    Stats {
+        /// The id of the board, as shown in the display listing
+        id: i64,
+    },
 }
 
 
@@ -1,13 +1,56 @@
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{params, Error};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Error, Row, Transaction};
 
-use crate::board;
+use std::collections::{HashMap, HashSet};
+
+use crate::cells::BoundingBox;
+use crate::emissions::Emission;
+use crate::{evolver, fitness, genome, map_elites, pareto, search};
+use crate::growth::LinearGrowth;
+use crate::stats::Generation;
+use crate::{board, Census, Stats};
+
+/// The width and height of the box a search found a board in, and every reason it was kept
+pub type SearchFind = (u32, u32, Vec<search::Reason>);
 
 pub struct Db {
     connection: PooledConnection<SqliteConnectionManager>,
 }
 
+/// The columns of the Boards table, in the order board_from_row expects them. We name them rather
+/// than select * b/c databases made by older versions had columns tacked on in a different order.
+const BOARD_COLUMNS: &str = "
+    id,
+    size,
+    starting_subdivisions,
+    starting_subdiv_utilization,
+    cells,
+    period,
+    iterations,
+    cycle_start,
+    growth_period,
+    growth_per_period,
+    final_population,
+    peak_population,
+    final_bbox_top,
+    final_bbox_left,
+    final_bbox_height,
+    final_bbox_width,
+    fitness,
+    seed_top,
+    seed_left,
+    seed_height,
+    seed_width,
+    run_id,
+    reproduces_as,
+    origin_similarity,
+    behavior,
+    novelty,
+    island
+";
+
 impl Db {
     pub fn new(connection: PooledConnection<SqliteConnectionManager>) -> Self {
         Self { connection }
@@ -26,132 +69,997 @@ impl Db {
             cells TEXT NOT NULL,
 
             period INTEGER,
-            iterations INTEGER NOT NULL
+            iterations INTEGER NOT NULL,
+            cycle_start INTEGER,
+            growth_period INTEGER,
+            growth_per_period INTEGER,
+            final_population INTEGER,
+            peak_population INTEGER,
+            final_bbox_top INTEGER,
+            final_bbox_left INTEGER,
+            final_bbox_height INTEGER,
+            final_bbox_width INTEGER,
+            fitness TEXT,
+            seed_top INTEGER,
+            seed_left INTEGER,
+            seed_height INTEGER,
+            seed_width INTEGER,
+            run_id INTEGER,
+            reproduces_as TEXT,
+            origin_similarity REAL,
+            behavior TEXT,
+            novelty REAL,
+            island INTEGER
             );
         ";
 
-        [create_boards].map(|query| {
+        let create_census = "
+        CREATE TABLE IF NOT EXISTS Census (
+            board_id INTEGER NOT NULL,
+            apgcode TEXT NOT NULL,
+            count INTEGER NOT NULL,
+
+            PRIMARY KEY (board_id, apgcode)
+            );
+        ";
+
+        let create_generations = "
+        CREATE TABLE IF NOT EXISTS Generations (
+            board_id INTEGER NOT NULL,
+            generation INTEGER NOT NULL,
+
+            population INTEGER NOT NULL,
+            births INTEGER NOT NULL,
+            deaths INTEGER NOT NULL,
+            bbox_top INTEGER,
+            bbox_left INTEGER,
+            bbox_height INTEGER,
+            bbox_width INTEGER,
+            mean_age REAL NOT NULL,
+
+            PRIMARY KEY (board_id, generation)
+            );
+        ";
+
+        let create_emissions = "
+        CREATE TABLE IF NOT EXISTS Emissions (
+            board_id INTEGER NOT NULL,
+            generation INTEGER NOT NULL,
+            apgcode TEXT NOT NULL,
+            direction TEXT NOT NULL
+            );
+        ";
+
+        // Everything a run of evolution was configured with, so it can be done again
+        let create_runs = "
+        CREATE TABLE IF NOT EXISTS Runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+
+            symmetry INTEGER NOT NULL,
+            cycle_detection TEXT NOT NULL,
+            record_stats INTEGER NOT NULL,
+            detect_emissions INTEGER NOT NULL,
+            remove_emissions INTEGER NOT NULL,
+            fitness TEXT NOT NULL,
+            crossover TEXT NOT NULL,
+            selection TEXT NOT NULL,
+            population_size INTEGER NOT NULL,
+            elites INTEGER NOT NULL,
+            replacement TEXT NOT NULL,
+            seed INTEGER,
+            mode TEXT,
+            descriptors TEXT,
+            islands INTEGER,
+            migration_interval INTEGER,
+            migrants INTEGER,
+            topology TEXT,
+            island_fitnesses TEXT
+            );
+        ";
+
+        // Where each board sits on a MAP-Elites grid, see map_elites. Boards in here are kept apart
+        // from the regular populations.
+        let create_elites = "
+        CREATE TABLE IF NOT EXISTS Elites (
+            board_id INTEGER PRIMARY KEY NOT NULL,
+            fitness TEXT NOT NULL,
+            descriptors TEXT NOT NULL,
+            x INTEGER NOT NULL,
+            y INTEGER NOT NULL
+            );
+        ";
+
+        // The Pareto front of each run of pareto evolution, as of the last time it changed. What
+        // the boards measured is copied in here, so the front can still be shown once they've been
+        // replaced. The measures are comma separated, in the order of the run's fitness terms.
+        let create_pareto_fronts = "
+        CREATE TABLE IF NOT EXISTS ParetoFronts (
+            run_id INTEGER NOT NULL,
+            board_id INTEGER NOT NULL,
+            initial_cells INTEGER NOT NULL,
+            iterations INTEGER NOT NULL,
+            measures TEXT NOT NULL
+            );
+        ";
+
+        // The boards a search of every pattern in a width by height box kept, see search. Like
+        // elites, boards in here are kept apart from the regular populations. A board kept for
+        // more than one reason has them all, comma separated.
+        let create_search_finds = "
+        CREATE TABLE IF NOT EXISTS SearchFinds (
+            board_id INTEGER PRIMARY KEY NOT NULL,
+            width INTEGER NOT NULL,
+            height INTEGER NOT NULL,
+            reason TEXT NOT NULL
+            );
+        ";
+
+        for query in [
+            create_boards,
+            create_census,
+            create_generations,
+            create_emissions,
+            create_runs,
+            create_elites,
+            create_pareto_fronts,
+            create_search_finds,
+        ] {
             connection.execute(query, params![]).unwrap();
-        });
+        }
+
+        // Columns that came after the table was first made
+        Db::add_column_if_missing(&connection, "Boards", "cycle_start", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "growth_period", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "growth_per_period", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "final_population", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "peak_population", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "final_bbox_top", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "final_bbox_left", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "final_bbox_height", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "final_bbox_width", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "fitness", "TEXT");
+        Db::add_column_if_missing(&connection, "Boards", "seed_top", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "seed_left", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "seed_height", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "seed_width", "INTEGER");
+        Db::add_column_if_missing(&connection, "Boards", "run_id", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "seed", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "mode", "TEXT");
+        Db::add_column_if_missing(&connection, "Boards", "reproduces_as", "TEXT");
+        Db::add_column_if_missing(&connection, "Boards", "origin_similarity", "REAL");
+        Db::add_column_if_missing(&connection, "Boards", "behavior", "TEXT");
+        Db::add_column_if_missing(&connection, "Boards", "novelty", "REAL");
+        Db::add_column_if_missing(&connection, "Runs", "descriptors", "TEXT");
+        Db::add_column_if_missing(&connection, "Boards", "island", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "islands", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "migration_interval", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "migrants", "INTEGER");
+        Db::add_column_if_missing(&connection, "Runs", "topology", "TEXT");
+        Db::add_column_if_missing(&connection, "Runs", "island_fitnesses", "TEXT");
+
+        // Boards from before fitness was configurable were all evolved under the default
+        connection
+            .execute(
+                "UPDATE Boards SET fitness = ? WHERE fitness IS NULL",
+                params![fitness::DEFAULT_SPEC],
+            )
+            .unwrap();
+    }
+
+    /// SQLite has no way to add a column only if it isn't there yet, so we look first. This is
+    /// how databases made by older versions of the program get brought up to date.
+    fn add_column_if_missing(connection: &Connection, table: &str, column: &str, definition: &str) {
+        let mut stmt = connection
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap();
+
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .any(|name| name.unwrap() == column);
+
+        if !exists {
+            connection
+                .execute(
+                    &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                    params![],
+                )
+                .unwrap();
+        }
     }
 
     /// Takes a number of cells and a board size and saves that board to the db
     /// Returns Result<board_id>
     pub fn save_board(&mut self, board: &board::Solved) -> Result<i64, Error> {
+        let mut transaction = self.connection.transaction()?;
+        let board_id = Db::insert_board(&mut transaction, board)?;
+        transaction.commit()?;
+
+        Ok(board_id)
+    }
+
+    /// Save a board and put it on a MAP-Elites grid. Either both happen or neither does, so a
+    /// retry never leaves a board off its grid or saved twice.
+    pub fn save_elite_board(
+        &mut self,
+        board: &board::Solved,
+        descriptors: &map_elites::Descriptors,
+        spot: (u32, u32),
+    ) -> Result<i64, Error> {
+        let mut transaction = self.connection.transaction()?;
+        let board_id = Db::insert_board(&mut transaction, board)?;
+        Db::insert_elite(&transaction, board_id, descriptors, spot)?;
+        transaction.commit()?;
+
+        Ok(board_id)
+    }
+
+    /// The board and everything that goes along with it, which all has to go in together
+    fn insert_board(transaction: &mut Transaction, board: &board::Solved) -> Result<i64, Error> {
         let cells = Db::serialize_cells(&board.initial.cells);
 
         // Insert one new board
-        self.connection.execute(
+        transaction.execute(
             "INSERT INTO Boards (
                 size,
                 starting_subdivisions,
                 starting_subdiv_utilization,
                 cells,
                 period,
-                iterations
-                ) VALUES (?, ?, ?, ?, ?, ?)",
+                iterations,
+                cycle_start,
+                growth_period,
+                growth_per_period,
+                final_population,
+                peak_population,
+                final_bbox_top,
+                final_bbox_left,
+                final_bbox_height,
+                final_bbox_width,
+                fitness,
+                seed_top,
+                seed_left,
+                seed_height,
+                seed_width,
+                run_id,
+                reproduces_as,
+                origin_similarity,
+                behavior,
+                novelty,
+                island
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 board.initial.size,
                 board.initial.starting_subdivisions,
                 board.initial.starting_subdiv_utilization,
                 cells,
                 board.period,
-                board.iterations
+                board.iterations,
+                board.cycle_start,
+                board.growth.map(|growth| growth.period),
+                board.growth.map(|growth| growth.growth_per_period),
+                board.final_population,
+                board.peak_population,
+                board.final_bounding_box.map(|b| b.top),
+                board.final_bounding_box.map(|b| b.left),
+                board.final_bounding_box.map(|b| b.height),
+                board.final_bounding_box.map(|b| b.width),
+                board.fitness,
+                board.initial.seed_region.top,
+                board.initial.seed_region.left,
+                board.initial.seed_region.height,
+                board.initial.seed_region.width,
+                board.run_id,
+                board.reproduces_as.map(|reproduction| reproduction.as_str()),
+                board.origin_similarity,
+                board.behavior.as_ref().map(|behavior| behavior.to_string()),
+                board.novelty,
+                board.island
             ],
         )?;
 
         // What was that last id?
-        let board_id = self.connection.last_insert_rowid();
+        let board_id = transaction.last_insert_rowid();
+
+        for (apgcode, count) in &board.census.objects {
+            transaction.execute(
+                "INSERT INTO Census (board_id, apgcode, count) VALUES (?, ?, ?)",
+                params![board_id, apgcode, count],
+            )?;
+        }
+
+        for emission in &board.emissions {
+            transaction.execute(
+                "INSERT INTO Emissions (board_id, generation, apgcode, direction) VALUES (?, ?, ?, ?)",
+                params![
+                    board_id,
+                    emission.generation,
+                    emission.apgcode,
+                    emission.direction.as_str()
+                ],
+            )?;
+        }
+
+        if let Some(stats) = &board.stats {
+            Db::save_stats(transaction, board_id, stats)?;
+        }
 
         Ok(board_id)
     }
 
+    /// Record the configuration of a run of evolution
+    /// Returns Result<run_id>
+    pub fn save_run(&mut self, config: &evolver::Config) -> Result<i64, Error> {
+        self.connection.execute(
+            "INSERT INTO Runs (
+                symmetry,
+                cycle_detection,
+                record_stats,
+                detect_emissions,
+                remove_emissions,
+                fitness,
+                crossover,
+                selection,
+                population_size,
+                elites,
+                replacement,
+                seed,
+                mode,
+                descriptors,
+                islands,
+                migration_interval,
+                migrants,
+                topology,
+                island_fitnesses
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                config.symmetry,
+                value_name(&config.cycle_detection),
+                config.record_stats,
+                config.detect_emissions,
+                config.remove_emissions,
+                config.fitness.to_string(),
+                value_name(&config.crossover),
+                config.selection.to_string(),
+                config.population_size,
+                config.elites,
+                value_name(&config.replacement),
+                // SQLite only does signed integers, this goes back the same way it came
+                config.seed.map(|seed| seed as i64),
+                value_name(&config.mode),
+                config.descriptors.to_string(),
+                config.islands,
+                config.migration_interval,
+                config.migrants,
+                value_name(&config.topology),
+                // Specs have commas of their own
+                config
+                    .island_fitnesses
+                    .iter()
+                    .map(|fitness| fitness.to_string())
+                    .collect::<Vec<String>>()
+                    .join(";")
+            ],
+        )?;
+
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// The configuration a run was started with
+    pub fn load_run(&self, run_id: i64) -> Result<evolver::Config, Error> {
+        self.connection.query_row(
+            "SELECT
+                symmetry,
+                cycle_detection,
+                record_stats,
+                detect_emissions,
+                remove_emissions,
+                fitness,
+                crossover,
+                selection,
+                population_size,
+                elites,
+                replacement,
+                seed,
+                mode,
+                descriptors,
+                islands,
+                migration_interval,
+                migrants,
+                topology,
+                island_fitnesses
+            FROM Runs WHERE id = ?",
+            params![run_id],
+            |row| {
+                Ok(evolver::Config {
+                    symmetry: row.get(0)?,
+                    cycle_detection: parse_value(&row.get::<_, String>(1)?),
+                    record_stats: row.get(2)?,
+                    detect_emissions: row.get(3)?,
+                    remove_emissions: row.get(4)?,
+                    fitness: parse_column(5, &row.get::<_, String>(5)?)?,
+                    crossover: parse_value(&row.get::<_, String>(6)?),
+                    selection: parse_column(7, &row.get::<_, String>(7)?)?,
+                    population_size: row.get(8)?,
+                    elites: row.get(9)?,
+                    replacement: parse_value(&row.get::<_, String>(10)?),
+                    // Runs from before seeds were recorded can't be done over
+                    seed: row.get::<_, Option<i64>>(11)?.map(|seed| seed as u64),
+                    mode: row
+                        .get::<_, Option<String>>(12)?
+                        .map_or(evolver::Mode::Standard, |mode| parse_value(&mode)),
+                    descriptors: row
+                        .get::<_, Option<String>>(13)?
+                        .map_or(Ok(Default::default()), |descriptors| {
+                            parse_column(13, &descriptors)
+                        })?,
+                    // Runs from before islands all shared a single population
+                    islands: row.get::<_, Option<bool>>(14)?.unwrap_or(false),
+                    migration_interval: row.get::<_, Option<usize>>(15)?.unwrap_or(50),
+                    migrants: row.get::<_, Option<usize>>(16)?.unwrap_or(1),
+                    topology: row
+                        .get::<_, Option<String>>(17)?
+                        .map_or(Default::default(), |topology| parse_value(&topology)),
+                    island_fitnesses: row
+                        .get::<_, Option<String>>(18)?
+                        .filter(|fitnesses| !fitnesses.is_empty())
+                        .map_or(Ok(vec![]), |fitnesses| {
+                            fitnesses.split(';').map(|f| parse_column(18, f)).collect()
+                        })?,
+                })
+            },
+        )
+    }
+
+    /// A run can be thousands of generations long, so they go in under a savepoint of their own
+    fn save_stats(
+        transaction: &mut Transaction,
+        board_id: i64,
+        stats: &Stats,
+    ) -> Result<(), Error> {
+        let savepoint = transaction.savepoint()?;
+
+        {
+            let mut stmt = savepoint.prepare(
+                "INSERT INTO Generations (
+                    board_id,
+                    generation,
+                    population,
+                    births,
+                    deaths,
+                    bbox_top,
+                    bbox_left,
+                    bbox_height,
+                    bbox_width,
+                    mean_age
+                    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )?;
+
+            for generation in &stats.generations {
+                let bounding_box = generation.bounding_box;
+
+                stmt.execute(params![
+                    board_id,
+                    generation.generation,
+                    generation.population,
+                    generation.births,
+                    generation.deaths,
+                    bounding_box.map(|b| b.top),
+                    bounding_box.map(|b| b.left),
+                    bounding_box.map(|b| b.height),
+                    bounding_box.map(|b| b.width),
+                    generation.mean_age
+                ])?;
+            }
+        }
+
+        savepoint.commit()
+    }
+
+    /// The recorded stats of a board, if there were any
+    pub fn load_stats(&self, board_id: i64) -> Result<Option<Stats>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT
+                generation,
+                population,
+                births,
+                deaths,
+                bbox_top,
+                bbox_left,
+                bbox_height,
+                bbox_width,
+                mean_age
+            FROM Generations WHERE board_id = ? ORDER BY generation",
+        )?;
+
+        let rows = stmt.query_map(params![board_id], |row| {
+            let top: Option<u32> = row.get(4)?;
+
+            let bounding_box = match top {
+                Some(top) => Some(BoundingBox {
+                    top,
+                    left: row.get(5)?,
+                    height: row.get(6)?,
+                    width: row.get(7)?,
+                }),
+                None => None,
+            };
+
+            Ok(Generation {
+                generation: row.get(0)?,
+                population: row.get(1)?,
+                births: row.get(2)?,
+                deaths: row.get(3)?,
+                bounding_box,
+                mean_age: row.get(8)?,
+            })
+        })?;
+
+        let generations = rows.collect::<Result<Vec<Generation>, Error>>()?;
+
+        if generations.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Stats { generations }))
+    }
+
     /// Get a single board from the db
     pub fn load_board(&self, board_id: i64) -> Result<board::Saved, Error> {
-        let (
-            id,
-            size,
-            starting_subdivisions,
-            starting_subdiv_utilization,
-            cells_str,
-            period,
-            iterations,
-        ): (i64, u32, usize, usize, String, usize, usize) = self.connection.query_row(
-            "SELECT * FROM Boards WHERE id = ?",
+        let mut board = self.connection.query_row(
+            &format!("SELECT {} FROM Boards WHERE id = ?", BOARD_COLUMNS),
             params![board_id],
-            |row| {
-                Ok((
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
-                    row.get(4)?,
-                    row.get(5)?,
-                    row.get(6)?,
-                ))
-            },
+            Db::board_from_row,
         )?;
 
-        let cells = Db::deserialize_cells(&cells_str);
+        if let Some(census) = self.load_censuses(Some(board_id))?.remove(&board_id) {
+            board.solved.census = census;
+        }
 
-        Ok(board::Saved {
-            id,
-            solved: board::Solved {
-                iterations,
-                period: Some(period),
-                initial: board::Initial {
-                    size,
-                    cells,
-                    starting_subdivisions,
-                    starting_subdiv_utilization,
-                },
-            },
-        })
+        if let Some(emissions) = self.load_emissions(Some(board_id))?.remove(&board_id) {
+            board.solved.emissions = emissions;
+        }
+
+        Ok(board)
     }
 
     /// Get all the boards from the db
     pub fn load_boards(&self) -> Result<Vec<board::Saved>, Error> {
-        let mut stmt = self.connection.prepare("SELECT * FROM Boards")?;
-
-        let boards_iter = stmt.query_map([], |row| {
-            let cells = Db::deserialize_cells(&row.get(4)?);
-            Ok(board::Saved {
-                id: row.get(0)?,
-                solved: board::Solved {
-                    iterations: row.get(6)?,
-                    period: Some(row.get(5)?),
-                    initial: board::Initial {
-                        size: row.get(1)?,
-                        cells,
-                        starting_subdivisions: row.get(2)?,
-                        starting_subdiv_utilization: row.get(3)?,
-                    },
-                },
-            })
-        })?;
+        let mut stmt = self
+            .connection
+            .prepare(&format!("SELECT {} FROM Boards", BOARD_COLUMNS))?;
 
+        let boards_iter = stmt.query_map([], Db::board_from_row)?;
+
+        let mut censuses = self.load_censuses(None)?;
+        let mut emissions = self.load_emissions(None)?;
         let mut boards = vec![];
 
         for board in boards_iter {
-            boards.push(board.unwrap());
+            let mut board = board.unwrap();
+
+            if let Some(census) = censuses.remove(&board.id) {
+                board.solved.census = census;
+            }
+
+            if let Some(emissions) = emissions.remove(&board.id) {
+                board.solved.emissions = emissions;
+            }
+
+            boards.push(board);
         }
 
         Ok(boards)
     }
 
+    /// Get all the boards that were evolved under the given fitness spec on the given island, or
+    /// in a shared population if no island's given, other than the ones on a MAP-Elites grid or
+    /// kept by a search
+    pub fn load_boards_evolved_under(
+        &self,
+        fitness: &str,
+        island: Option<u32>,
+    ) -> Result<Vec<board::Saved>, Error> {
+        let elites = self.load_elite_ids(None)?;
+        let finds = self.load_finds()?;
+        let mut boards = self.load_boards()?;
+        boards.retain(|board| {
+            fitness::same_spec(&board.solved.fitness, fitness)
+                && board.solved.island == island
+                && !elites.contains(&board.id)
+                && !finds.contains_key(&board.id)
+        });
+        Ok(boards)
+    }
+
+    /// Put a board on a MAP-Elites grid
+    pub fn save_elite(
+        &mut self,
+        board_id: i64,
+        descriptors: &map_elites::Descriptors,
+        (x, y): (u32, u32),
+    ) -> Result<(), Error> {
+        Db::insert_elite(&self.connection, board_id, descriptors, (x, y))
+    }
+
+    fn insert_elite(
+        connection: &Connection,
+        board_id: i64,
+        descriptors: &map_elites::Descriptors,
+        (x, y): (u32, u32),
+    ) -> Result<(), Error> {
+        connection.execute(
+            "INSERT OR REPLACE INTO Elites (board_id, fitness, descriptors, x, y)
+                SELECT id, fitness, ?, ?, ? FROM Boards WHERE id = ?",
+            params![descriptors.to_string(), x, y, board_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// The boards on the grid of elites evolved under the given fitness spec and laid out by the
+    /// given descriptors
+    pub fn load_elites(
+        &self,
+        fitness: &str,
+        descriptors: &map_elites::Descriptors,
+    ) -> Result<Vec<board::Saved>, Error> {
+        let elites = self.load_elite_ids(Some((fitness, descriptors)))?;
+        let mut boards = self.load_boards()?;
+        boards.retain(|board| elites.contains(&board.id));
+        Ok(boards)
+    }
+
+    /// Every grid of elites there is, one for each fitness spec and pair of descriptors
+    pub fn load_grids(&self) -> Result<Vec<map_elites::Grid>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT fitness, descriptors, x, y, board_id FROM Elites
+                ORDER BY fitness, descriptors",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })?;
+
+        let mut grids: Vec<map_elites::Grid> = vec![];
+
+        for row in rows {
+            let (fitness, descriptors, x, y, board_id) = row?;
+            let descriptors = descriptors.parse().unwrap();
+
+            match grids.last_mut() {
+                Some(grid) if grid.fitness == fitness && grid.descriptors == descriptors => {
+                    grid.elites.insert((x, y), board_id);
+                }
+                _ => grids.push(map_elites::Grid {
+                    fitness,
+                    descriptors,
+                    elites: [((x, y), board_id)].into_iter().collect(),
+                }),
+            }
+        }
+
+        Ok(grids)
+    }
+
+    /// Replace whatever front was saved for the run before
+    pub fn save_front(&mut self, front: &pareto::Front) -> Result<(), Error> {
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "DELETE FROM ParetoFronts WHERE run_id = ?",
+            params![front.run_id],
+        )?;
+
+        for point in &front.points {
+            let measures: Vec<String> = point.measures.iter().map(|m| m.to_string()).collect();
+
+            transaction.execute(
+                "INSERT INTO ParetoFronts (run_id, board_id, initial_cells, iterations, measures)
+                    VALUES (?, ?, ?, ?, ?)",
+                params![
+                    front.run_id,
+                    point.board_id,
+                    point.initial_cells,
+                    point.iterations,
+                    measures.join(",")
+                ],
+            )?;
+        }
+
+        transaction.commit()
+    }
+
+    /// The saved front of every run that has one
+    pub fn load_fronts(&self) -> Result<Vec<pareto::Front>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT ParetoFronts.run_id, Runs.fitness, board_id, initial_cells, iterations, measures
+                FROM ParetoFronts JOIN Runs ON Runs.id = ParetoFronts.run_id
+                ORDER BY ParetoFronts.run_id",
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let measures: String = row.get(5)?;
+
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                pareto::Point {
+                    board_id: row.get(2)?,
+                    initial_cells: row.get(3)?,
+                    iterations: row.get(4)?,
+                    measures: measures.split(',').map(|m| m.parse().unwrap()).collect(),
+                },
+            ))
+        })?;
+
+        let mut fronts: Vec<pareto::Front> = vec![];
+
+        for row in rows {
+            let (run_id, fitness, point) = row?;
+
+            match fronts.last_mut() {
+                Some(front) if front.run_id == run_id => front.points.push(point),
+                _ => fronts.push(pareto::Front {
+                    run_id,
+                    fitness: fitness.parse().unwrap(),
+                    points: vec![point],
+                }),
+            }
+        }
+
+        Ok(fronts)
+    }
+
+    /// Save a board a search of a width by height box kept
+    pub fn save_find(
+        &mut self,
+        width: u32,
+        height: u32,
+        find: &search::Find,
+    ) -> Result<i64, Error> {
+        let mut transaction = self.connection.transaction()?;
+        let board_id = Db::insert_board(&mut transaction, &find.board)?;
+        let reasons: Vec<&str> = find.reasons.iter().map(search::Reason::as_str).collect();
+
+        transaction.execute(
+            "INSERT INTO SearchFinds (board_id, width, height, reason) VALUES (?, ?, ?, ?)",
+            params![board_id, width, height, reasons.join(",")],
+        )?;
+        transaction.commit()?;
+
+        Ok(board_id)
+    }
+
+    /// Delete every board an earlier search of a box this size kept
+    pub fn delete_finds(&mut self, width: u32, height: u32) -> Result<(), Error> {
+        let board_ids: Vec<i64> = self
+            .connection
+            .prepare("SELECT board_id FROM SearchFinds WHERE width = ? AND height = ?")?
+            .query_map(params![width, height], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        for board_id in &board_ids {
+            self.delete_board(board_id)?;
+        }
+
+        Ok(())
+    }
+
+    /// The width and height of the box each board kept by a search was found in, and why it was
+    /// kept, by board id
+    pub fn load_finds(&self) -> Result<HashMap<i64, SearchFind>, Error> {
+        let mut stmt = self
+            .connection
+            .prepare("SELECT board_id, width, height, reason FROM SearchFinds")?;
+
+        let rows = stmt.query_map([], |row| {
+            let reasons: String = row.get(3)?;
+            let reasons = reasons
+                .split(',')
+                .map(|reason| reason.parse().unwrap())
+                .collect();
+
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?, reasons)))
+        })?;
+
+        rows.collect()
+    }
+
+    /// The ids of every board on a grid of elites, or just those on one particular grid
+    fn load_elite_ids(
+        &self,
+        grid: Option<(&str, &map_elites::Descriptors)>,
+    ) -> Result<HashSet<i64>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT board_id FROM Elites WHERE ?1 IS NULL OR (fitness = ?1 AND descriptors = ?2)",
+        )?;
+
+        let (fitness, descriptors) = grid.unzip();
+        let rows = stmt.query_map(
+            params![fitness, descriptors.map(|descriptors| descriptors.to_string())],
+            |row| row.get(0),
+        )?;
+
+        rows.collect()
+    }
+
+    /// The census of every board, or just the one board if an id is given, by board id
+    fn load_censuses(&self, board_id: Option<i64>) -> Result<HashMap<i64, Census>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT board_id, apgcode, count FROM Census WHERE ?1 IS NULL OR board_id = ?1",
+        )?;
+
+        let rows = stmt.query_map(params![board_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+
+        let mut censuses: HashMap<i64, Census> = HashMap::new();
+
+        for row in rows {
+            let (board_id, apgcode, count): (i64, String, usize) = row?;
+            censuses
+                .entry(board_id)
+                .or_default()
+                .objects
+                .insert(apgcode, count);
+        }
+
+        Ok(censuses)
+    }
+
+    /// The emissions of every board, or just the one board if an id is given, by board id
+    fn load_emissions(&self, board_id: Option<i64>) -> Result<HashMap<i64, Vec<Emission>>, Error> {
+        let mut stmt = self.connection.prepare(
+            "SELECT board_id, generation, apgcode, direction FROM Emissions
+            WHERE ?1 IS NULL OR board_id = ?1
+            ORDER BY board_id, generation",
+        )?;
+
+        let rows = stmt.query_map(params![board_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+
+        let mut emissions: HashMap<i64, Vec<Emission>> = HashMap::new();
+
+        for row in rows {
+            let (board_id, generation, apgcode, direction): (i64, usize, String, String) = row?;
+            emissions.entry(board_id).or_default().push(Emission {
+                generation,
+                apgcode,
+                direction: direction.parse().unwrap(),
+            });
+        }
+
+        Ok(emissions)
+    }
+
+    /// Build a board out of a row selected with BOARD_COLUMNS
+    fn board_from_row(row: &Row) -> Result<board::Saved, Error> {
+        let cells = Db::deserialize_cells(&row.get::<_, String>(4)?);
+
+        let final_bbox_top: Option<u32> = row.get(12)?;
+        let final_bounding_box = match final_bbox_top {
+            Some(top) => Some(BoundingBox {
+                top,
+                left: row.get(13)?,
+                height: row.get(14)?,
+                width: row.get(15)?,
+            }),
+            None => None,
+        };
+
+        // Boards from before the seed region could evolve were all seeded in the middle
+        let size = row.get(1)?;
+        let seed_top: Option<u32> = row.get(17)?;
+        let seed_region = match seed_top {
+            Some(top) => BoundingBox {
+                top,
+                left: row.get(18)?,
+                height: row.get(19)?,
+                width: row.get(20)?,
+            },
+            None => genome::central_region(size),
+        };
+
+        let growth_period: Option<usize> = row.get(8)?;
+        let growth = match growth_period {
+            Some(period) => Some(LinearGrowth {
+                period,
+                growth_per_period: row.get(9)?,
+            }),
+            None => None,
+        };
+
+        Ok(board::Saved {
+            id: row.get(0)?,
+            solved: board::Solved {
+                iterations: row.get(6)?,
+                period: row.get(5)?,
+                cycle_start: row.get(7)?,
+                census: Census::default(),
+                stats: None,
+                emissions: vec![],
+                growth,
+                // Older boards don't have these, they'll measure as 0
+                final_population: row.get::<_, Option<usize>>(10)?.unwrap_or(0),
+                peak_population: row.get::<_, Option<usize>>(11)?.unwrap_or(0),
+                final_bounding_box,
+                fitness: row.get(16)?,
+                run_id: row.get(21)?,
+                reproduces_as: row
+                    .get::<_, Option<String>>(22)?
+                    .map(|reproduction| reproduction.parse().unwrap()),
+                origin_similarity: row.get(23)?,
+                behavior: row
+                    .get::<_, Option<String>>(24)?
+                    .map(|behavior| behavior.parse().unwrap()),
+                novelty: row.get(25)?,
+                island: row.get(26)?,
+                initial: board::Initial {
+                    size,
+                    cells,
+                    starting_subdivisions: row.get(2)?,
+                    starting_subdiv_utilization: row.get(3)?,
+                    seed_region,
+                },
+            },
+        })
+    }
+
+    /// Replace a board's novelty score, which changes as the archive it was measured against grows
+    pub fn update_novelty(&mut self, board_id: i64, novelty: f64) -> Result<(), Error> {
+        self.connection.execute(
+            "UPDATE Boards SET novelty = ? WHERE id = ?",
+            params![novelty, board_id],
+        )?;
+
+        Ok(())
+    }
+
     pub fn delete_board(&mut self, board_id: &i64) -> Result<(), Error> {
         self.connection
             .execute("DELETE FROM Boards WHERE id = ?", params![board_id])?;
+        self.connection
+            .execute("DELETE FROM Census WHERE board_id = ?", params![board_id])?;
+        self.connection
+            .execute("DELETE FROM Generations WHERE board_id = ?", params![board_id])?;
+        self.connection
+            .execute("DELETE FROM Emissions WHERE board_id = ?", params![board_id])?;
+        self.connection
+            .execute("DELETE FROM Elites WHERE board_id = ?", params![board_id])?;
+        self.connection
+            .execute("DELETE FROM SearchFinds WHERE board_id = ?", params![board_id])?;
 
         Ok(())
     }
 
+    /// The highest id any board's been saved under, 0 if there haven't been any. Ids of deleted
+    /// boards count too, so they never get reused.
+    pub fn get_max_board_id(&self) -> Result<i64, Error> {
+        let max_board_id: Option<i64> = self.connection.query_row(
+            "SELECT max(id) FROM (
+                SELECT id FROM Boards UNION ALL SELECT seq FROM sqlite_sequence WHERE name = 'Boards'
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(max_board_id.unwrap_or(0))
+    }
+
     /// How many boards are there in the db?
     pub fn get_board_count(&self) -> Result<u64, Error> {
         let count = self
             .connection
             .query_row("SELECT COUNT(*) FROM Boards", params![], |row| {
-                Ok(row.get(0)?)
+                row.get(0)
             })?;
 
         Ok(count)
@@ -159,13 +1067,18 @@ impl Db {
 
     /// For simplicity's sake, even though it's not technically correct, we're stringifying the
     /// board's cells and storing them in a single db cell in the Boards table.
-    fn serialize_cells(cells: &Vec<(u32, u32)>) -> String {
+    fn serialize_cells(cells: &[(u32, u32)]) -> String {
         let mut cells: String = cells.iter().map(|(i, j)| format!("{}-{},", i, j)).collect();
         cells.pop(); // we don't want the last |
         cells
     }
 
-    fn deserialize_cells(cells_str: &String) -> Vec<(u32, u32)> {
+    fn deserialize_cells(cells_str: &str) -> Vec<(u32, u32)> {
+        // A board with no cells at all serializes to nothing, not to one empty cell
+        if cells_str.is_empty() {
+            return vec![];
+        }
+
         let cells = cells_str
             .split(",")
             .map(|str| {
@@ -180,6 +1093,22 @@ impl Db {
     }
 }
 
+/// How a command line option is spelled, like "region-swap"
+fn value_name(value: &impl clap::ValueEnum) -> String {
+    value.to_possible_value().unwrap().get_name().to_string()
+}
+
+/// The other way around from value_name
+fn parse_value<T: clap::ValueEnum>(name: &str) -> T {
+    T::from_str(name, false).unwrap()
+}
+
+/// Parse a spec saved in the given column, failing the way any other bad column would
+fn parse_column<T: std::str::FromStr<Err = String>>(column: usize, spec: &str) -> Result<T, Error> {
+    spec.parse()
+        .map_err(|err: String| Error::FromSqlConversionFailure(column, Type::Text, err.into()))
+}
+
 #[test]
 fn saving_and_loading_boards() {
     let manager = SqliteConnectionManager::memory();
@@ -187,15 +1116,74 @@ fn saving_and_loading_boards() {
     Db::initialize(pool.get().unwrap());
     let mut db = Db::new(pool.get().unwrap());
 
+    let config = evolver::Config {
+        crossover: genome::Crossover::Uniform,
+        replacement: evolver::Replacement::Generational,
+        elites: 2,
+        seed: Some(u64::MAX),
+        mode: evolver::Mode::SelfReproducing,
+        descriptors: "census-diversity,longevity".parse().unwrap(),
+        islands: true,
+        topology: crate::islands::Topology::All,
+        island_fitnesses: vec!["period".parse().unwrap(), "longevity=2,period=3".parse().unwrap()],
+        ..evolver::Config::default()
+    };
+    let run_id = db.save_run(&config).unwrap();
+    assert_eq!(config, db.load_run(run_id).unwrap());
+
+    // A spec that doesn't parse is an error like any other bad column, rather than a panic
+    let broken_id = db.save_run(&config).unwrap();
+    db.connection
+        .execute("UPDATE Runs SET fitness = 'nonsense' WHERE id = ?", params![broken_id])
+        .unwrap();
+    assert!(matches!(
+        db.load_run(broken_id),
+        Err(Error::FromSqlConversionFailure(5, Type::Text, _))
+    ));
+
     let board = board::Solved {
         initial: board::Initial {
             size: 10,
             starting_subdivisions: 5,
             starting_subdiv_utilization: 3,
             cells: vec![(1, 1), (2, 2), (3, 3)],
+            seed_region: BoundingBox { top: 1, left: 2, height: 6, width: 7 },
         },
         period: Some(10),
         iterations: 100,
+        cycle_start: Some(90),
+        census: Census {
+            objects: [(String::from("xs4_33"), 2)].into_iter().collect(),
+        },
+        stats: Some(Stats {
+            generations: vec![Generation {
+                generation: 0,
+                population: 3,
+                births: 0,
+                deaths: 0,
+                bounding_box: Some(BoundingBox { top: 1, left: 1, height: 3, width: 3 }),
+                mean_age: 1.0,
+            }],
+        }),
+        emissions: vec![Emission {
+            generation: 48,
+            apgcode: String::from("xq4_153"),
+            direction: crate::emissions::Direction::SouthEast,
+        }],
+        growth: Some(LinearGrowth {
+            period: 30,
+            growth_per_period: 5,
+        }),
+        final_population: 8,
+        peak_population: 12,
+        final_bounding_box: Some(BoundingBox { top: 2, left: 3, height: 4, width: 5 }),
+        fitness: String::from(fitness::DEFAULT_SPEC),
+        run_id: Some(run_id),
+        reproduces_as: Some(board::Reproduction::Spaceship),
+        origin_similarity: Some(0.5),
+        behavior: Some(crate::novelty::Behavior(vec![1.0, 0.25, 7.5])),
+        novelty: Some(2.5),
+        island: Some(3),
     };
 
     let board_id = db.save_board(&board).unwrap();
@@ -205,9 +1193,25 @@ fn saving_and_loading_boards() {
     assert_eq!(board.initial.size, retrieved_board.solved.initial.size);
     assert_eq!(board.iterations, retrieved_board.solved.iterations);
     assert_eq!(board.period, retrieved_board.solved.period);
+    assert_eq!(board.cycle_start, retrieved_board.solved.cycle_start);
+    assert_eq!(board.census, retrieved_board.solved.census);
+    assert_eq!(board.stats, db.load_stats(board_id).unwrap());
+    assert_eq!(board.emissions, retrieved_board.solved.emissions);
+    assert_eq!(board.growth, retrieved_board.solved.growth);
+    assert_eq!(board.final_population, retrieved_board.solved.final_population);
+    assert_eq!(board.peak_population, retrieved_board.solved.peak_population);
+    assert_eq!(board.final_bounding_box, retrieved_board.solved.final_bounding_box);
+    assert_eq!(board.fitness, retrieved_board.solved.fitness);
+    assert_eq!(board.run_id, retrieved_board.solved.run_id);
+    assert_eq!(board.reproduces_as, retrieved_board.solved.reproduces_as);
+    assert_eq!(board.origin_similarity, retrieved_board.solved.origin_similarity);
+    assert_eq!(board.behavior, retrieved_board.solved.behavior);
+    assert_eq!(board.novelty, retrieved_board.solved.novelty);
+    assert_eq!(board.island, retrieved_board.solved.island);
     assert_eq!(board.initial.cells, retrieved_board.solved.initial.cells);
     assert_eq!(board.initial.starting_subdivisions, retrieved_board.solved.initial.starting_subdivisions);
     assert_eq!(board.initial.starting_subdiv_utilization, retrieved_board.solved.initial.starting_subdiv_utilization);
+    assert_eq!(board.initial.seed_region, retrieved_board.solved.initial.seed_region);
 
     let id = retrieved_board.id;
     assert!(id > 0);
@@ -215,4 +1219,118 @@ fn saving_and_loading_boards() {
     let retrieved_boards = db.load_boards().unwrap();
 
     assert_eq!(retrieved_boards.len(), 1);
+
+    // Once it's on a grid, the board only comes back with the grid
+    db.save_elite(id, &config.descriptors, (1, 7)).unwrap();
+
+    assert!(db.load_boards_evolved_under(&board.fitness, Some(3)).unwrap().is_empty());
+    assert_eq!(db.load_elites(&board.fitness, &config.descriptors).unwrap().len(), 1);
+    assert_eq!(
+        db.load_grids().unwrap(),
+        vec![crate::map_elites::Grid {
+            fitness: board.fitness.clone(),
+            descriptors: config.descriptors,
+            elites: [((1, 7), id)].into_iter().collect(),
+        }]
+    );
+
+    // Saving a run's front again replaces it
+    let mut front = pareto::Front {
+        run_id,
+        fitness: config.fitness.clone(),
+        points: vec![pareto::Point::new(&config.fitness, &retrieved_board)],
+    };
+    db.save_front(&front).unwrap();
+    front.points[0].iterations = 200;
+    db.save_front(&front).unwrap();
+
+    assert_eq!(db.load_fronts().unwrap(), vec![front]);
+
+    // A search's finds are kept apart too, until the next search of the same box replaces them
+    let reasons = vec![search::Reason::Spaceship, search::Reason::LongestLived];
+    let find = search::Find {
+        reasons: reasons.clone(),
+        board: board.clone(),
+    };
+    let find_id = db.save_find(3, 3, &find).unwrap();
+
+    assert_eq!(
+        db.load_finds().unwrap().get(&find_id),
+        Some(&(3, 3, reasons))
+    );
+    assert_eq!(db.load_boards().unwrap().len(), 2);
+
+    db.delete_finds(3, 3).unwrap();
+    assert!(db.load_finds().unwrap().is_empty());
+    assert_eq!(db.load_boards().unwrap().len(), 1);
+
+    // Boards with no cells make it back as boards with no cells
+    assert!(Db::deserialize_cells(&Db::serialize_cells(&[])).is_empty());
+}
+
+#[test]
+fn failed_saves_leave_nothing_behind() {
+    let manager = SqliteConnectionManager::memory();
+    let pool = r2d2::Pool::new(manager).unwrap();
+    Db::initialize(pool.get().unwrap());
+    let mut db = Db::new(pool.get().unwrap());
+
+    // Make the very last insert a board needs fail
+    db.connection
+        .execute(
+            "CREATE TRIGGER no_generations BEFORE INSERT ON Generations
+                BEGIN SELECT RAISE(ABORT, 'no generations'); END",
+            params![],
+        )
+        .unwrap();
+
+    let board = board::Solved {
+        initial: board::Initial {
+            size: 10,
+            starting_subdivisions: 1,
+            starting_subdiv_utilization: 1,
+            cells: vec![(1, 1), (1, 2), (2, 1), (2, 2)],
+            seed_region: genome::central_region(10),
+        },
+        period: Some(1),
+        iterations: 2,
+        cycle_start: Some(0),
+        census: Census {
+            objects: [(String::from("xs4_33"), 1)].into_iter().collect(),
+        },
+        stats: Some(Stats {
+            generations: vec![Generation {
+                generation: 0,
+                population: 4,
+                births: 0,
+                deaths: 0,
+                bounding_box: None,
+                mean_age: 1.0,
+            }],
+        }),
+        emissions: vec![],
+        growth: None,
+        final_population: 4,
+        peak_population: 4,
+        final_bounding_box: None,
+        fitness: String::from(fitness::DEFAULT_SPEC),
+        run_id: None,
+        reproduces_as: None,
+        origin_similarity: None,
+        behavior: None,
+        novelty: None,
+        island: None,
+    };
+    let descriptors: map_elites::Descriptors = "census-diversity,longevity".parse().unwrap();
+
+    assert!(db.save_board(&board).is_err());
+    assert!(db.save_elite_board(&board, &descriptors, (0, 0)).is_err());
+
+    assert_eq!(db.get_board_count().unwrap(), 0);
+    let census_rows: i64 = db
+        .connection
+        .query_row("SELECT COUNT(*) FROM Census", params![], |row| row.get(0))
+        .unwrap();
+    assert_eq!(census_rows, 0);
+    assert!(db.load_grids().unwrap().is_empty());
 }
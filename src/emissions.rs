@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::census::{self, Object};
+use crate::cells::Connectivity;
+use crate::snapshot::Velocity;
+use crate::Cells;
+
+// The emission detector's responsibility is to:
+// * Notice spaceships, like gliders, that break off of a board's main pattern and fly away
+// * Count each one once, even though it'll be seen again every time we look
+// * Optionally take them off the board. The board wraps around, so otherwise they'd eventually
+//   come back around and crash into whatever sent them off.
+//
+// It works by looking at the board every so often, splitting it into clusters that are well apart
+// from each other, and classifying the small ones with the census. A spaceship heading away from
+// everything else on the board is an emission. Ships we've already counted are tracked by where
+// we expect them to be next time, given their velocity.
+
+/// How many generations to go between looks at the board
+const INTERVAL: usize = 16;
+
+/// How many empty cells have to separate a cluster from everything else for it to count as having
+/// broken away
+const SEPARATION: u32 = 4;
+
+/// Spaceships bigger than this aren't worth looking for, it'd mostly be classifying debris
+const MAX_POPULATION: usize = 40;
+
+/// How far off of where we expected it a tracked ship can be and still be the same ship
+const TRACKING_TOLERANCE: i64 = 2;
+
+/// A spaceship that broke away from the rest of the board
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emission {
+    /// The generation it was first seen off on its own
+    pub generation: usize,
+
+    pub apgcode: String,
+
+    pub direction: Direction,
+}
+
+/// Which way something's headed, with north being the top of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// The closest of the 8 directions to the way something's moving. A move that's mostly along
+    /// one axis counts as straight along it. None for things that aren't moving.
+    pub fn of(velocity: &Velocity) -> Option<Direction> {
+        let (di, dj) = (velocity.di, velocity.dj);
+
+        let vertical = if di.abs() * 2 < dj.abs() { 0 } else { di.signum() };
+        let horizontal = if dj.abs() * 2 < di.abs() { 0 } else { dj.signum() };
+
+        let direction = match (vertical, horizontal) {
+            (-1, 0) => Direction::North,
+            (-1, 1) => Direction::NorthEast,
+            (0, 1) => Direction::East,
+            (1, 1) => Direction::SouthEast,
+            (1, 0) => Direction::South,
+            (1, -1) => Direction::SouthWest,
+            (0, -1) => Direction::West,
+            (-1, -1) => Direction::NorthWest,
+            _ => return None,
+        };
+
+        Some(direction)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::NorthEast => "north-east",
+            Direction::East => "east",
+            Direction::SouthEast => "south-east",
+            Direction::South => "south",
+            Direction::SouthWest => "south-west",
+            Direction::West => "west",
+            Direction::NorthWest => "north-west",
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Direction::ALL
+            .into_iter()
+            .find(|direction| direction.as_str() == s)
+            .ok_or_else(|| format!("unknown direction {}", s))
+    }
+}
+
+/// A ship we've already counted, and where it was last seen
+struct Tracked {
+    apgcode: String,
+    top_left: (u32, u32),
+    velocity: Velocity,
+    generation: usize,
+}
+
+pub struct EmissionDetector {
+    remove: bool,
+    tracked: Vec<Tracked>,
+    classified: HashMap<Vec<(u32, u32)>, Option<Object>>,
+    pub emissions: Vec<Emission>,
+}
+
+impl EmissionDetector {
+    /// Pass remove as true to take ships off the board as soon as they're found
+    pub fn new(remove: bool) -> EmissionDetector {
+        EmissionDetector {
+            remove,
+            tracked: vec![],
+            classified: HashMap::new(),
+            emissions: vec![],
+        }
+    }
+
+    /// Look over the board for ships that have broken away. Meant to be called after every step,
+    /// it only actually looks every so often. Ships the board starts out with haven't broken away
+    /// from anything, so generation 0 is never looked at.
+    pub fn inspect(&mut self, cells: &mut Cells, generation: usize) {
+        if generation == 0 || !generation.is_multiple_of(INTERVAL) {
+            return;
+        }
+
+        let clusters = cells.clusters(Connectivity::Moore, SEPARATION + 1);
+
+        // With nothing else on the board, there's nothing to have broken away from
+        if clusters.len() < 2 {
+            return;
+        }
+
+        let width = cells.size() as i64 + 1;
+        let population = cells.num_living_cells() as i64;
+
+        // Where everything is on average, to tell if a ship is heading away from it. Wrapping
+        // makes this rough, but it only needs to be roughly right.
+        let (sum_i, sum_j) = cells
+            .living_cells()
+            .iter()
+            .fold((0, 0), |(si, sj), (i, j)| (si + *i as i64, sj + *j as i64));
+
+        let mut still_tracked = vec![];
+
+        for cluster in clusters {
+            if cluster.population() > MAX_POPULATION {
+                continue;
+            }
+
+            let object = self
+                .classified
+                .entry(cluster.pattern.clone())
+                .or_insert_with_key(|pattern| census::classify(pattern));
+
+            let object = match object {
+                Some(object) if object.is_spaceship() => object.clone(),
+                _ => continue,
+            };
+
+            let top_left = (cluster.bounding_box.top, cluster.bounding_box.left);
+
+            // Already counted? Then just keep tracking it.
+            if let Some(index) = self.find_tracked(&object, top_left, generation, width) {
+                let mut tracked = self.tracked.remove(index);
+                tracked.top_left = top_left;
+                tracked.generation = generation;
+                still_tracked.push(tracked);
+                continue;
+            }
+
+            // The center of everything but this cluster, and whether the ship is moving away
+            let others = population - cluster.population() as i64;
+            let (cluster_i, cluster_j) = cluster
+                .cells
+                .iter()
+                .fold((0, 0), |(si, sj), (i, j)| (si + *i as i64, sj + *j as i64));
+            let away_i = wrap(
+                cluster_i / cluster.population() as i64 - (sum_i - cluster_i) / others,
+                width,
+            );
+            let away_j = wrap(
+                cluster_j / cluster.population() as i64 - (sum_j - cluster_j) / others,
+                width,
+            );
+
+            if away_i * object.velocity.di + away_j * object.velocity.dj <= 0 {
+                continue;
+            }
+
+            if let Some(direction) = Direction::of(&object.velocity) {
+                self.emissions.push(Emission {
+                    generation,
+                    apgcode: object.apgcode.clone(),
+                    direction,
+                });
+            }
+
+            if self.remove {
+                cluster.cells.iter().for_each(|(i, j)| cells.remove(*i, *j));
+            } else {
+                still_tracked.push(Tracked {
+                    apgcode: object.apgcode,
+                    top_left,
+                    velocity: object.velocity,
+                    generation,
+                });
+            }
+        }
+
+        // Anything we didn't see this time has crashed into something or wrapped out of view
+        self.tracked = still_tracked;
+    }
+
+    /// Is there a ship we're already tracking that would have moved to this spot by now?
+    fn find_tracked(
+        &self,
+        object: &Object,
+        top_left: (u32, u32),
+        generation: usize,
+        width: i64,
+    ) -> Option<usize> {
+        self.tracked.iter().position(|tracked| {
+            let periods = (generation - tracked.generation) as i64 / tracked.velocity.period as i64;
+            let expected_i = tracked.top_left.0 as i64 + tracked.velocity.di * periods;
+            let expected_j = tracked.top_left.1 as i64 + tracked.velocity.dj * periods;
+
+            tracked.apgcode == object.apgcode
+                && wrap(top_left.0 as i64 - expected_i, width).abs() <= TRACKING_TOLERANCE
+                && wrap(top_left.1 as i64 - expected_j, width).abs() <= TRACKING_TOLERANCE
+        })
+    }
+}
+
+/// The shortest way around the board for a difference in position
+fn wrap(delta: i64, width: i64) -> i64 {
+    let delta = delta.rem_euclid(width);
+
+    if delta > width / 2 {
+        delta - width
+    } else {
+        delta
+    }
+}
+
+#[test]
+fn detects_glider_leaving_once() {
+    for remove in [false, true] {
+        let mut cells = Cells::new(60);
+        cells.birth_multiple(&[(10, 10), (10, 11), (11, 10), (11, 11)]);
+        cells.birth_multiple(&[(20, 21), (21, 22), (22, 20), (22, 21), (22, 22)]);
+        let mut game = crate::Game::new(None, cells, None, None);
+        let mut detector = EmissionDetector::new(remove);
+
+        for _ in 0..64 {
+            game.step();
+            let generation = game.generation();
+            detector.inspect(&mut game.cells, generation);
+        }
+
+        assert_eq!(detector.emissions.len(), 1);
+        assert_eq!(detector.emissions[0].apgcode, "xq4_153");
+        assert_eq!(detector.emissions[0].direction, Direction::SouthEast);
+
+        let expected_population = if remove { 4 } else { 9 };
+        assert_eq!(game.cells.num_living_cells(), expected_population);
+    }
+}
+
+#[test]
+fn leaves_the_initial_board_alone() {
+    let mut cells = Cells::new(60);
+    cells.birth_multiple(&[(10, 10), (10, 11), (11, 10), (11, 11)]);
+    cells.birth_multiple(&[(20, 21), (21, 22), (22, 20), (22, 21), (22, 22)]);
+    cells.commit();
+    let mut detector = EmissionDetector::new(true);
+
+    detector.inspect(&mut cells, 0);
+
+    assert!(detector.emissions.is_empty());
+    assert_eq!(cells.num_living_cells(), 9);
+}
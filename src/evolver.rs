@@ -1,5 +1,34 @@
-use crate::{board, Cells};
-use rand::{thread_rng, Rng};
+use crate::emissions::EmissionDetector;
+use crate::fitness::{self, Fitness};
+use crate::genome::{self, Crossover};
+use crate::growth::GrowthDetector;
+use crate::islands::{self, Island};
+use crate::map_elites;
+use crate::novelty::Behavior;
+use crate::pareto;
+use crate::population::{Members, Population};
+use crate::selection::{self, Selection};
+use crate::{board, Cells, Census, Stats};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Arc;
+
+/// How often a child is seeded fresh from its parents' mated seed parameters rather than
+/// inheriting their cells. Keeps new material coming in, and is the only time the seed parameters
+/// do anything. The rest of the time they're carried along unused, drifting by mutation, so
+/// selection only acts on them through the boards they seed.
+const RESEED_RATE: f64 = 0.1;
+
+/// The shortest period an oscillator that comes back to its initial state needs to count as
+/// reproducing. Still lifes (period 1) and period 2 oscillators like the blinker, toad and beacon
+/// do it trivially, and random boards are full of them. Spaceships count at any period.
+const MIN_REPRODUCING_PERIOD: usize = 3;
+
+/// In methuselah mode, the initial cells have to fit in a square this big
+const METHUSELAH_SIDE: u32 = 8;
+
+/// In methuselah mode, the most initial cells a board can have
+const METHUSELAH_MAX_CELLS: usize = 12;
 
 // The evolver's responsibility is to:
 // * Orchestrate a single thread of evolution
@@ -7,35 +36,285 @@ use rand::{thread_rng, Rng};
 //   * House all the logic about evolution
 
 pub struct Evolver {
-    db: crate::Db,
+    /// Shared with every other evolver thread
+    population: Arc<Population>,
+
     size: u32,
+    config: Config,
+
+    /// The run this evolver is part of, recorded with every board it saves
+    run_id: i64,
+
+    thread_num: u32,
+
+    /// Where all of this evolver's randomness comes from, seeded from the run's seed and the
+    /// thread number, so a single threaded run can be done over again exactly
+    rng: StdRng,
+
+    /// The islands this one sends migrants to, if it's an island
+    neighbors: Vec<Island>,
+
+    /// How many boards this evolver's gone through, for timing migrations
+    evolved: usize,
+}
+
+/// Knobs for how a run of evolution behaves, mostly set from the command line. Saved with each
+/// run, see Db::save_run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// Also treat rotated and reflected states as repeats when looking for loops
+    pub symmetry: bool,
+
+    /// Whether to keep every generation's hash around when looking for loops, or use Brent's
+    /// constant memory algorithm
+    pub cycle_detection: crate::snapshot::Strategy,
+
+    /// Keep a time series of every generation of each board, and save it with the board
+    pub record_stats: bool,
+
+    /// Look for spaceships breaking away from each board
+    pub detect_emissions: bool,
+
+    /// Take spaceships off the board once they've broken away, so they don't wrap around and
+    /// crash back into the board. Implies detect_emissions.
+    pub remove_emissions: bool,
+
+    /// What evolution is trying to maximize. Boards are only ever compared against other boards
+    /// evolved under the same fitness.
+    pub fitness: fitness::Weighted,
+
+    /// How parents' cells get combined into their child's
+    pub crossover: Crossover,
+
+    /// How parents are picked out of the population
+    pub selection: selection::Strategy,
+
+    /// How many boards to keep around
+    pub population_size: usize,
+
+    /// How many of the fittest boards are kept no matter what. Has to be less than
+    /// population_size.
+    pub elites: usize,
+
+    /// How new boards make their way into the population
+    pub replacement: Replacement,
+
+    /// Where the run's randomness comes from. Each thread gets its own random numbers from this,
+    /// so a single threaded run with the same seed and database makes the same boards. Runs from
+    /// before seeds were recorded don't have one, and get a fresh one each time they're used.
+    pub seed: Option<u64>,
+
+    /// What kind of board the run is looking for
+    pub mode: Mode,
+
+    /// What boards are laid out on the grid by, in map-elites mode
+    pub descriptors: map_elites::Descriptors,
+
+    /// Give each thread a population of its own, see islands
+    pub islands: bool,
+
+    /// How many boards each island evolves between sending off migrants
+    pub migration_interval: usize,
+
+    /// How many of an island's fittest boards go out each time it sends off migrants
+    pub migrants: usize,
+
+    /// Which islands migrants go to
+    pub topology: islands::Topology,
+
+    /// What each island evolves for, in turn, starting over from the first once they run out.
+    /// Every island evolves for fitness if there aren't any.
+    pub island_fitnesses: Vec<fitness::Weighted>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            symmetry: false,
+            cycle_detection: Default::default(),
+            record_stats: false,
+            detect_emissions: false,
+            remove_emissions: false,
+            fitness: Default::default(),
+            crossover: Default::default(),
+            selection: Default::default(),
+            population_size: 10,
+            elites: 0,
+            replacement: Default::default(),
+            seed: None,
+            mode: Default::default(),
+            descriptors: Default::default(),
+            islands: false,
+            migration_interval: 50,
+            migrants: 1,
+            topology: Default::default(),
+            island_fitnesses: vec![],
+        }
+    }
+}
+
+/// What kind of board a run is looking for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mode {
+    /// Whatever the fitness asks for
+    #[default]
+    Standard,
+
+    /// Boards that come back around to their own initial state, either in place (oscillators)
+    /// or moved over (spaceships). Keeps track of how close every board gets, so the ones that
+    /// nearly make it can be bred from.
+    SelfReproducing,
+
+    /// Small patterns that take a long time to settle down, like the R-pentomino and acorn.
+    /// Initial cells are kept to a few inside a small square.
+    Methuselah,
+
+    /// Boards that do something that hasn't been seen before. Each board's behavior is compared
+    /// against the population and an archive of past behaviors, and the further it is from them
+    /// the fitter it is, see novelty.
+    Novelty,
+
+    /// The fittest board of every kind, with kinds told apart by two descriptors. Each spot on
+    /// the grid keeps its own elite, which only a fitter board of the same kind can replace, so
+    /// there's no population size. Parents are picked from the grid uniformly, see map_elites.
+    MapElites,
+
+    /// Every term of the fitness at once, each kept as its own objective rather than added up.
+    /// Terms with a negative weight are minimized, like "longevity,-initial-cells". Boards are
+    /// ranked by Pareto dominance, and the lowest ranked one is what gets replaced, so the
+    /// replacement policy and elites don't apply. See pareto.
+    Pareto,
+}
+
+impl Config {
+    /// What the given island evolves for
+    pub fn island_fitness(&self, island: u32) -> fitness::Weighted {
+        match self.island_fitnesses.len() {
+            0 => self.fitness.clone(),
+            len => self.island_fitnesses[island as usize % len].clone(),
+        }
+    }
+
+    /// Whether boards need a behavior to have their novelty scored, either b/c that's the mode
+    /// or b/c novelty's somewhere in the fitness
+    fn scores_novelty(&self) -> bool {
+        self.mode == Mode::Novelty
+            || self
+                .fitness
+                .terms
+                .iter()
+                .any(|(objective, _)| *objective == fitness::Objective::Novelty)
+    }
+}
+
+impl Mode {
+    /// What to evolve for when no fitness is given
+    pub fn default_fitness(&self) -> fitness::Weighted {
+        match self {
+            Mode::Standard => fitness::Weighted::default(),
+            Mode::SelfReproducing => "self-reproduction".parse().unwrap(),
+            Mode::Methuselah => "stabilization=1,final-population=1".parse().unwrap(),
+            Mode::Novelty => "novelty=1".parse().unwrap(),
+            Mode::MapElites => fitness::Weighted::default(),
+            Mode::Pareto => "longevity,-initial-cells".parse().unwrap(),
+        }
+    }
+}
+
+/// How a new board gets into a full population
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Replacement {
+    /// Replace the least fit board, but only if the new one is fitter
+    #[default]
+    ReplaceWorst,
+
+    /// Replace a random board from the less fit half, however fit the new one is. Keeps more
+    /// variety around than always replacing the worst.
+    ReplaceRandomBelowMedian,
+
+    /// Hold on to new boards until there are enough of them to replace everything but the elites
+    /// in one go
+    Generational,
+}
+
+/// Each thread's own seed, worked out from the run's. Just adding the thread number would have
+/// thread 1 of one run playing out the same as thread 0 of the run seeded one higher, so the seed
+/// is scrambled before the thread's mixed in, and again after.
+fn thread_seed(seed: u64, thread_num: u32) -> u64 {
+    splitmix64(splitmix64(seed) ^ thread_num as u64)
+}
+
+/// A single step of the SplitMix64 generator, which scatters numbers that are close together all
+/// over the place
+fn splitmix64(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
 }
 
 impl Evolver {
-    pub fn new(size: u32, db: crate::Db) -> Self {
-        Self { db, size }
+    pub fn new(
+        size: u32,
+        population: Arc<Population>,
+        config: Config,
+        run_id: i64,
+        thread_num: u32,
+    ) -> Self {
+        let rng = StdRng::seed_from_u64(thread_seed(
+            config.seed.unwrap_or_else(rand::random),
+            thread_num,
+        ));
+
+        Self {
+            population,
+            size,
+            config,
+            run_id,
+            thread_num,
+            rng,
+            neighbors: vec![],
+            evolved: 0,
+        }
     }
 
-    /// Measure the fitness for a "saved" board
-    pub fn measure_fitness_saved(board: &board::Saved) -> isize {
-        measure(board.solved.period, board.solved.iterations)
+    /// Send migrants off to these islands every so often, see Config::migration_interval
+    pub fn with_neighbors(mut self, neighbors: Vec<Island>) -> Self {
+        self.neighbors = neighbors;
+        self
     }
 
-    /// Measure the fitness for a "solved" board
-    pub fn measure_fitness_solved(board: &board::Solved) -> isize {
-        measure(board.period, board.iterations)
+    /// Measure the fitness of a board under this evolver's fitness function
+    pub fn measure_fitness(&self, board: &board::Solved) -> f64 {
+        self.config.fitness.measure(board)
     }
 
     /// Variables we mate over:
-    /// * number of cells
+    /// * the cells themselves, see genome
     /// * number of starting subdivisions
     /// * dispersement over starting subdivisions
-    pub fn mate(board1: &board::Saved, board2: &board::Saved) -> board::Initial {
+    /// * the region the subdivisions are laid out over
+    ///
+    /// Only the cells go into the child's board though. The rest are the seed parameters, which
+    /// only lay out cells when the child's reseeded, see RESEED_RATE.
+    pub fn mate(&mut self, board1: &board::Saved, board2: &board::Saved) -> board::Initial {
+        let size = board1.solved.initial.size;
+
         // Mate
+        let cells = genome::crossover(
+            self.config.crossover,
+            size,
+            &board1.solved.initial.cells,
+            &board2.solved.initial.cells,
+            &mut self.rng,
+        );
+
+        let num_cells = (board1.solved.initial.cells.len() + board2.solved.initial.cells.len()) / 2;
+        let seed_region = genome::blend_regions(
+            board1.solved.initial.seed_region,
+            board2.solved.initial.seed_region,
+        );
 
-        // Get the average of the two boards' cell counts as a starting point
-        let mut num_cells =
-            (board1.solved.initial.cells.len() + board2.solved.initial.cells.len()) / 2;
         let mut starting_subdivisions = (board1.solved.initial.starting_subdivisions
             + board2.solved.initial.starting_subdivisions)
             / 2;
@@ -44,209 +323,795 @@ impl Evolver {
             / 2;
 
         // Mutate
-        num_cells = mutate_integer(&num_cells, 25);
-        starting_subdivisions = mutate_integer(&starting_subdivisions, 3);
-        starting_subdiv_utilization = mutate_integer(&starting_subdiv_utilization, 3);
+        let cells = genome::mutate(size, &cells, &mut self.rng);
+        let seed_region = genome::mutate_region(size, seed_region, &mut self.rng);
+        starting_subdivisions = mutate_integer(&starting_subdivisions, 3, &mut self.rng).max(1);
+        starting_subdiv_utilization =
+            mutate_integer(&starting_subdiv_utilization, 3, &mut self.rng).max(1);
 
         // we can't utilize more than exists
         starting_subdiv_utilization =
             std::cmp::min(starting_subdivisions, starting_subdiv_utilization);
 
+        // Every so often, start over from the seed parameters instead. A child that came out with
+        // no cells at all, like from parents that don't overlap, always does, since there's
+        // nothing to run.
+        let cells = if cells.is_empty() || self.rng.gen_bool(RESEED_RATE) {
+            genome::seed(
+                seed_region,
+                starting_subdivisions,
+                starting_subdiv_utilization,
+                mutate_integer(&num_cells, 25, &mut self.rng).max(1),
+                &mut self.rng,
+            )
+        } else {
+            cells
+        };
+
         board::Initial {
-            size: board1.solved.initial.size,
-            cells: random_cells(board1.solved.initial.size, num_cells),
+            size,
+            cells,
             starting_subdivisions,
             starting_subdiv_utilization,
+            seed_region,
         }
     }
 
     /// Via some strategy, gets a new board ready to solve. If there're enough boards in the DB,
     /// it'll mate two and return the child. Otherwise it'll create a random one.
-    fn get_next_board(&self) -> board::Initial {
-        let board_count = self.db.get_board_count().unwrap();
+    fn get_next_board(&mut self) -> board::Initial {
+        let population = Arc::clone(&self.population);
+        let parents = population.read(|boards| {
+            (boards.len() >= 2).then(|| self.retrieve_two_fit_individuals(boards))
+        });
 
-        if board_count < 2 {
-            return self.generate_random_starter_board();
-        }
+        let mut board = match parents {
+            Some((board1, board2)) => self.mate(&board1, &board2),
+            None => self.generate_random_starter_board(),
+        };
 
-        let (board1, board2) = self.retrieve_two_fit_individuals();
+        if self.config.mode == Mode::Methuselah {
+            board.cells = genome::confine(
+                &board.cells,
+                METHUSELAH_SIDE,
+                METHUSELAH_MAX_CELLS,
+                &mut self.rng,
+            );
+            board.seed_region.height = board.seed_region.height.min(METHUSELAH_SIDE);
+            board.seed_region.width = board.seed_region.width.min(METHUSELAH_SIDE);
+        }
 
-        Evolver::mate(&board1, &board2)
+        board
     }
 
-    /// Pick two individuals from the database at random
-    /// panics if there are fewer than two boards saved in the db
-    fn retrieve_two_fit_individuals(&self) -> (board::Saved, board::Saved) {
-        let mut boards = self.db.load_boards().unwrap();
-
+    /// Pick two different individuals from the population with the configured selection strategy
+    /// panics if there are fewer than two boards in the population
+    fn retrieve_two_fit_individuals(
+        &mut self,
+        boards: &[board::Saved],
+    ) -> (board::Saved, board::Saved) {
         if boards.len() < 2 {
-            panic!("retrieve_two_fit_individuals called with fewer than two boards in the db");
+            panic!(
+                "retrieve_two_fit_individuals called with fewer than two boards in the population"
+            );
         }
 
-        let first_index = thread_rng().gen_range(0..boards.len());
-        let second_index = thread_rng().gen_range(0..boards.len() - 1);
+        let mut fitnesses: Vec<f64> = match self.config.mode {
+            Mode::Pareto => pareto::fitnesses(&self.pareto_scores(boards.iter())),
+            _ => boards
+                .iter()
+                .map(|board| self.measure_fitness(&board.solved))
+                .collect(),
+        };
+
+        // Every elite on the grid is the best of its kind, so they all get an equal shot
+        let selection = match self.config.mode {
+            Mode::MapElites => selection::Strategy::Truncation(1.0),
+            _ => self.config.selection,
+        };
 
-        (boards.remove(first_index), boards.remove(second_index))
+        let first_index = selection.select(&fitnesses, &mut self.rng);
+        fitnesses.remove(first_index);
+
+        // Indexes past the first one are off by one now that it's out
+        let mut second_index = selection.select(&fitnesses, &mut self.rng);
+        if second_index >= first_index {
+            second_index += 1;
+        }
+
+        (boards[first_index].clone(), boards[second_index].clone())
     }
 
     // Maybe we don't have enough boards in the pool -- sometimes we need to just make
     // a random one
-    fn generate_random_starter_board(&self) -> board::Initial {
-        let num_cells = thread_rng().gen_range(1..=1000);
-        let starting_subdivisions = thread_rng().gen_range(1..=15);
-        let mut starting_subdiv_utilization = thread_rng().gen_range(1..=15);
+    fn generate_random_starter_board(&mut self) -> board::Initial {
+        let starting_subdivisions = self.rng.gen_range(1..=15);
+        let mut starting_subdiv_utilization = self.rng.gen_range(1..=15);
 
         // We cannot utilize more than what we have
         starting_subdiv_utilization =
             std::cmp::min(starting_subdivisions, starting_subdiv_utilization);
 
+        let (num_cells, seed_region) = match self.config.mode {
+            Mode::Methuselah => (
+                self.rng.gen_range(3..=METHUSELAH_MAX_CELLS),
+                genome::central_square(self.size, METHUSELAH_SIDE),
+            ),
+            _ => (
+                self.rng.gen_range(1..=1000),
+                genome::central_region(self.size),
+            ),
+        };
+
         board::Initial {
             size: self.size,
             starting_subdivisions,
             starting_subdiv_utilization,
-            cells: random_cells(self.size, num_cells),
+            seed_region,
+            cells: genome::seed(
+                seed_region,
+                starting_subdivisions,
+                starting_subdiv_utilization,
+                num_cells,
+                &mut self.rng,
+            ),
         }
     }
 
-    pub fn begin_evolving(&mut self, thread_num: u32) {
-        let size = self.size;
+    pub fn begin_evolving(&mut self) {
+        loop {
+            self.evolve_one();
+        }
+    }
 
-        // Variables we're iterating over, and can bring around via mating
-        // * Number of initial cells
-        // * Size of starting area, right now it's 1/5 right in the middle
+    /// Breed, solve and place a single board
+    pub fn evolve_one(&mut self) {
+        // Get our strategically generated new board
+        let board = self.get_next_board();
 
-        loop {
-            // Get our strategically generated new board
-            let board = self.get_next_board();
+        // After this, the board's been solved. Now we'll check it to see its fitness.
+        let mut new_solved_board = solve(&self.config, board, Some(self.run_id), self.thread_num);
 
-            // populate our cells container
-            let mut cells = Cells::new(size);
-            cells.birth_multiple(&board.cells);
+        // Novelty depends on what's been seen so far, so it's scored here rather than in solve
+        if let Some(behavior) = &new_solved_board.behavior {
+            new_solved_board.novelty = Some(self.population.novelty(behavior, &mut self.rng));
+        }
 
-            let snapshot = crate::Snapshot::new(size);
+        // Everything from here on happens with the population locked, so no other thread can
+        // replace the same board out from under us
+        let population = Arc::clone(&self.population);
+        population.update(|members| {
+            if self.config.mode == Mode::MapElites {
+                self.place_elite(members, new_solved_board);
+                return;
+            }
 
-            // Game's responsibility is to provide the step() function and a few
-            // winning metrics.
-            let mut game = crate::Game::new(Some(snapshot), cells, None);
+            if self.config.mode == Mode::Pareto {
+                self.replace_dominated(members, new_solved_board);
+                return;
+            }
 
-            // Iterate a single board
-            loop {
-                game.step();
+            if members.boards().len() < self.config.population_size {
+                // If we don't have a full population yet, every configuration gets in.
+                println!(
+                    "thread {} found insuficiently populated database and is saving board",
+                    self.thread_num
+                );
+                members.add(new_solved_board);
+                return;
+            }
+
+            // The elites are the fittest few, and are never replaced
+            let ranked = self.rank(members.boards());
+            let replaceable = &ranked[self.config.elites.min(ranked.len())..];
+
+            match self.config.replacement {
+                Replacement::ReplaceWorst => {
+                    self.replace_worst(members, new_solved_board, replaceable)
+                }
 
-                // Bail if it's a barren death land
-                if game.cells.num_living_cells() == 0 {
-                    break;
+                Replacement::ReplaceRandomBelowMedian => {
+                    let below_median = &ranked[self.config.elites.max(ranked.len() / 2)..];
+                    self.replace_random(members, new_solved_board, below_median)
                 }
 
-                // Bail if we're in an infinite loop
-                if let Some(snapshot) = &game.snapshot {
-                    if snapshot.has_repeat() {
-                        break;
+                Replacement::Generational => {
+                    let waiting = members.hold_for_next_generation(new_solved_board);
+
+                    if waiting >= self.config.population_size - self.config.elites {
+                        println!(
+                            "thread {} is replacing {} boards with a new generation",
+                            self.thread_num,
+                            replaceable.len()
+                        );
+
+                        for (id, _) in replaceable {
+                            members.remove(*id);
+                        }
+
+                        for child in members.take_next_generation() {
+                            members.add(child);
+                        }
                     }
                 }
             }
+        });
 
-            // After that loop, the board's been solved. Now we'll check it to see
-            // its fitness.
-            let new_solved_board = board::Solved {
-                initial: board::Initial {
-                    size,
-                    cells: board.cells,
-                    starting_subdivisions: board.starting_subdivisions,
-                    starting_subdiv_utilization: board.starting_subdiv_utilization,
-                },
-                iterations: game.iterations,
-                period: game.snapshot.unwrap().period(),
-            };
-
-            let boards = self.db.load_boards().unwrap();
-
-            // We'll keep the best 10 configurations.
-            if boards.len() < 10 {
-                // If we don't have 10 yet, every configuration is in the best 10.
-                println!(
-                    "thread {} found insuficiently populated database and is saving board",
-                    thread_num
-                );
-                self.db
-                    .save_board(&new_solved_board)
-                    .expect("error saving board");
-            } else {
-                // Find the least fit saved board
-                let mut least_fit = boards.get(0).unwrap();
-                for board in &boards {
-                    if Evolver::measure_fitness_saved(&least_fit)
-                        > Evolver::measure_fitness_saved(board)
+        self.evolved += 1;
+        let interval = self.config.migration_interval.max(1);
+        if !self.neighbors.is_empty() && self.evolved.is_multiple_of(interval) {
+            self.migrate();
+        }
+    }
+
+    /// Send copies of this island's fittest boards to each neighboring island. There, each one
+    /// takes the place of the least fit board if it's any fitter by that island's fitness.
+    fn migrate(&self) {
+        let migrants: Vec<board::Solved> = self.population.read(|boards| {
+            let ranked = self.rank(boards);
+            ranked
+                .iter()
+                .take(self.config.migrants)
+                .map(|(id, _)| {
+                    let board = boards.iter().find(|board| board.id == *id).unwrap();
+                    board.solved.clone()
+                })
+                .collect()
+        });
+
+        for neighbor in &self.neighbors {
+            let arrived = neighbor.population.update(|members| {
+                let mut arrived = 0;
+
+                for migrant in &migrants {
+                    let mut migrant = migrant.clone();
+                    migrant.fitness = neighbor.fitness.to_string();
+                    migrant.island = Some(neighbor.id);
+
+                    // The same board coming back around doesn't need a second spot
+                    if members
+                        .boards()
+                        .iter()
+                        .any(|board| board.solved.initial.cells == migrant.initial.cells)
                     {
-                        least_fit = board;
+                        continue;
                     }
-                }
 
-                // Check our newly solved board against the least fit of the saved boards
-                if Evolver::measure_fitness_solved(&new_solved_board)
-                    > Evolver::measure_fitness_saved(&least_fit)
-                {
-                    println!(
-                        "thread {} made a more fit board! It has {} cells, starting_subdivisions of {}, starting_subdiv_utilization of {}
-                        and is of period {:?} and has {} iterations",
-                        thread_num,
-                        new_solved_board.initial.cells.len(),
-                        new_solved_board.initial.starting_subdivisions,
-                        new_solved_board.initial.starting_subdiv_utilization,
-                        new_solved_board.period,
-                        new_solved_board.iterations
-                    );
-                    // If we're more fit than the least fit one, replace it in the db
-                    self.db.save_board(&new_solved_board).unwrap();
-                    self.db.delete_board(&least_fit.id).unwrap();
-                } else {
-                    println!(
-                        "thread {} made an unfit board with {} cells, starting_subdivisions of {} and starting_subdiv_utilization of {}",
-                        thread_num,
-                        new_solved_board.initial.cells.len(),
-                        new_solved_board.initial.starting_subdivisions,
-                        new_solved_board.initial.starting_subdiv_utilization
-                    );
+                    if members.boards().len() < self.config.population_size {
+                        members.add(migrant);
+                        arrived += 1;
+                        continue;
+                    }
+
+                    let least_fit = members
+                        .boards()
+                        .iter()
+                        .map(|board| (board.id, neighbor.fitness.measure(&board.solved)))
+                        .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                    if let Some((id, fitness)) = least_fit {
+                        if neighbor.fitness.measure(&migrant) > fitness {
+                            members.remove(id);
+                            members.add(migrant);
+                            arrived += 1;
+                        }
+                    }
                 }
+
+                arrived
+            });
+
+            if arrived > 0 {
+                println!(
+                    "thread {} sent {} boards to island {}",
+                    self.thread_num, arrived, neighbor.id
+                );
             }
         }
     }
-}
 
-// TODO We should evolve over the numbers in range_i too
-fn random_cells(size: u32, num: usize) -> Vec<(u32, u32)> {
-    let mut cells = vec![];
+    /// Put the board in its spot on the grid, if the spot's empty or it beats the elite there
+    fn place_elite(&self, members: &mut Members, new: board::Solved) {
+        let descriptors = self.config.descriptors;
+        let cell = descriptors.cell(&new);
+
+        let elite = members
+            .boards()
+            .iter()
+            .find(|board| descriptors.cell(&board.solved) == cell)
+            .map(|board| (board.id, self.measure_fitness(&board.solved)));
+
+        let new_fitness = self.measure_fitness(&new);
+
+        match elite {
+            None => {
+                println!(
+                    "thread {} found a board for an empty spot on the grid at {:?}",
+                    self.thread_num, cell
+                );
+                members.add(new);
+            }
+
+            Some((id, fitness)) if new_fitness > fitness => {
+                println!(
+                    "thread {} found a new elite at {:?}, with a fitness of {} over {}",
+                    self.thread_num, cell, new_fitness, fitness
+                );
+                members.remove(id);
+                members.add(new);
+            }
+
+            Some(_) => {}
+        }
+    }
+
+    /// Every board's scores on each of the fitness's objectives, see pareto::scores
+    fn pareto_scores<'a>(&self, boards: impl Iterator<Item = &'a board::Saved>) -> Vec<Vec<f64>> {
+        boards
+            .map(|board| pareto::scores(&self.config.fitness, &board.solved))
+            .collect()
+    }
+
+    /// Swap the new board in for whichever board comes last in the Pareto order, unless that's the
+    /// new board itself. Then record the front.
+    fn replace_dominated(&self, members: &mut Members, new: board::Solved) {
+        if members.boards().len() >= self.config.population_size {
+            let mut scores = self.pareto_scores(members.boards().iter());
+            scores.push(pareto::scores(&self.config.fitness, &new));
+
+            let last = *pareto::order(&scores).last().unwrap();
+            if last == members.boards().len() {
+                println!(
+                    "thread {} made a board that didn't make the cut",
+                    self.thread_num
+                );
+                return;
+            }
+
+            let last_id = members.boards()[last].id;
+            println!(
+                "thread {} made a board that makes the cut, replacing board {}",
+                self.thread_num, last_id
+            );
+            members.remove(last_id);
+        }
+
+        members.add(new);
+
+        let scores = self.pareto_scores(members.boards().iter());
+        let front = pareto::fronts(&scores).swap_remove(0);
+        let points = front
+            .into_iter()
+            .map(|index| pareto::Point::new(&self.config.fitness, &members.boards()[index]))
+            .collect();
 
-    for _ in 0..num {
-        let range_i = (size * 2) / 5..(size * 3) / 5;
-        let range_j = range_i.clone();
-        let rand_i = thread_rng().gen_range(range_i);
-        let rand_j = thread_rng().gen_range(range_j);
-        cells.push((rand_i, rand_j));
+        members.set_front(pareto::Front {
+            run_id: self.run_id,
+            fitness: self.config.fitness.clone(),
+            points,
+        });
     }
 
-    cells
+    /// The ids of the boards along with their fitness, fittest first
+    fn rank(&self, boards: &[board::Saved]) -> Vec<(i64, f64)> {
+        let mut ranked: Vec<(i64, f64)> = boards
+            .iter()
+            .map(|board| (board.id, self.measure_fitness(&board.solved)))
+            .collect();
+
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked
+    }
+
+    /// Swap the new board in for the least fit of the replaceable ones, if it's any fitter
+    fn replace_worst(
+        &self,
+        members: &mut Members,
+        new_solved_board: board::Solved,
+        replaceable: &[(i64, f64)],
+    ) {
+        let (least_fit_id, least_fitness) = match replaceable.last() {
+            Some(least_fit) => *least_fit,
+            None => return,
+        };
+
+        // Check our newly solved board against the least fit of the saved boards
+        if self.measure_fitness(&new_solved_board) > least_fitness {
+            println!(
+                "thread {} made a more fit board! It has {} cells, starting_subdivisions of {}, starting_subdiv_utilization of {}
+                and is of period {:?} and has {} iterations, for a fitness of {}",
+                self.thread_num,
+                new_solved_board.initial.cells.len(),
+                new_solved_board.initial.starting_subdivisions,
+                new_solved_board.initial.starting_subdiv_utilization,
+                new_solved_board.period,
+                new_solved_board.iterations,
+                self.measure_fitness(&new_solved_board)
+            );
+            // If we're more fit than the least fit one, replace it
+            members.add(new_solved_board);
+            members.remove(least_fit_id);
+        } else {
+            println!(
+                "thread {} made an unfit board with {} cells, starting_subdivisions of {} and starting_subdiv_utilization of {}",
+                self.thread_num,
+                new_solved_board.initial.cells.len(),
+                new_solved_board.initial.starting_subdivisions,
+                new_solved_board.initial.starting_subdiv_utilization
+            );
+        }
+    }
+
+    /// Swap the new board in for one of the candidates picked at random, however fit it is
+    fn replace_random(
+        &mut self,
+        members: &mut Members,
+        new_solved_board: board::Solved,
+        candidates: &[(i64, f64)],
+    ) {
+        if candidates.is_empty() {
+            return;
+        }
+
+        let (replaced_id, _) = candidates[self.rng.gen_range(0..candidates.len())];
+
+        println!(
+            "thread {} replaced board {} with a board of fitness {}",
+            self.thread_num,
+            replaced_id,
+            self.measure_fitness(&new_solved_board)
+        );
+
+        members.add(new_solved_board);
+        members.remove(replaced_id);
+    }
 }
 
-/// This is actually the main fitness measurement right here
-fn measure(period: Option<usize>, iterations: usize) -> isize {
-    match period {
-        Some(period) => (period * 20 + iterations) as isize,
-        None => iterations as isize,
+/// Run a board until it dies, repeats or is found to be growing forever, and gather up
+/// everything there is to know about how it went. The thread number's only for telling whoever's
+/// watching where finds came from.
+pub fn solve(
+    config: &Config,
+    board: board::Initial,
+    run_id: Option<i64>,
+    thread_num: u32,
+) -> board::Solved {
+    let size = board.size;
+
+    // populate our cells container
+    let mut cells = Cells::new(size);
+    cells.birth_multiple(&board.cells);
+
+    let mut snapshot = crate::Snapshot::new(size).with_strategy(config.cycle_detection);
+    if config.symmetry {
+        snapshot = snapshot.with_symmetry();
+    }
+    if config.mode == Mode::SelfReproducing {
+        snapshot = snapshot.with_origin_similarity();
+    }
+
+    // Game's responsibility is to provide the step() function and a few
+    // winning metrics.
+    let stats = config.record_stats.then(Stats::new);
+    let mut game = crate::Game::new(Some(snapshot), cells, None, stats);
+
+    let mut emissions = (config.detect_emissions || config.remove_emissions)
+        .then(|| EmissionDetector::new(config.remove_emissions));
+
+    let mut growth_detector = GrowthDetector::new();
+    let mut growth = None;
+    let mut peak_population = 0;
+
+    // The population curve, for novelty's behavior descriptor. The initial cells are loaded in on
+    // the first step, so that's where it starts.
+    let mut populations = config.scores_novelty().then(Vec::new);
+
+    // Iterate a single board
+    loop {
+        game.step();
+
+        if let Some(emissions) = &mut emissions {
+            let generation = game.generation();
+            emissions.inspect(&mut game.cells, generation);
+        }
+
+        peak_population = peak_population.max(game.cells.num_living_cells());
+        if let Some(populations) = &mut populations {
+            populations.push(game.cells.num_living_cells());
+        }
+
+        // Bail if it's a barren death land
+        if game.cells.num_living_cells() == 0 {
+            break;
+        }
+
+        // Bail if we're in an infinite loop
+        if let Some(snapshot) = &game.snapshot {
+            if snapshot.has_repeat() {
+                break;
+            }
+        }
+
+        // Bail if it's a gun or puffer, it'll never repeat
+        growth_detector.record(&game.cells);
+        growth = growth_detector.detect();
+        if growth.is_some() {
+            break;
+        }
+    }
+
+    if let Some(growth) = growth {
+        println!(
+            "thread {} found a board that grows by {} cells every {} generations",
+            thread_num, growth.growth_per_period, growth.period
+        );
+    }
+
+    // Whatever the board did while the snapshot made sure of its repeat was just the loop going
+    // around again, so it's left out of the count and the history
+    let lag = game
+        .snapshot
+        .as_ref()
+        .map_or(0, crate::Snapshot::confirmation_lag);
+    let iterations = game.iterations - lag;
+    let last_generation = game.generation() - lag;
+    if let Some(stats) = &mut game.stats {
+        stats
+            .generations
+            .retain(|generation| generation.generation <= last_generation);
+    }
+    if let Some(populations) = &mut populations {
+        populations.truncate(populations.len() - lag);
+    }
+    if let Some(emissions) = &mut emissions {
+        emissions
+            .emissions
+            .retain(|emission| emission.generation <= last_generation);
+    }
+
+    let snapshot = game.snapshot.unwrap();
+
+    // A repeat that's only a rotated or flipped copy hasn't gone all the way around the loop, so
+    // the full period and how far it moves over it have to be found without symmetry
+    let velocity = match snapshot.symmetry() {
+        Some(symmetry) if symmetry != crate::snapshot::Symmetry::Identity => {
+            snapshot.period().and_then(|period| {
+                crate::snapshot::find_translation(size, &game.cells.living_cells(), period)
+            })
+        }
+        _ => snapshot.velocity(),
+    };
+
+    // Translation doesn't count against a repeat, so a spaceship shows up here as soon as
+    // it's gone through a single period.
+    if let Some(velocity) = velocity {
+        if !velocity.is_stationary() {
+            println!(
+                "thread {} found a spaceship of period {} moving at {}",
+                thread_num, velocity.period, velocity
+            );
+        }
+    }
+
+    if let Some(symmetry) = snapshot.symmetry() {
+        if symmetry != crate::snapshot::Symmetry::Identity {
+            println!(
+                "thread {} found a board that repeats as a {:?} of itself",
+                thread_num, symmetry
+            );
+        }
+    }
+
+    let cycle_start = snapshot.cycle_start().or_else(|| {
+        snapshot.period().and_then(|period| {
+            crate::snapshot::find_cycle_start(
+                size,
+                &board.cells,
+                period,
+                config.symmetry,
+                config.remove_emissions,
+                iterations,
+            )
+        })
+    });
+    let period = velocity
+        .map(|velocity| velocity.period)
+        .or_else(|| snapshot.period());
+
+    // Looping right from the start means the initial state comes back around
+    let reproduces_as = match (cycle_start, velocity) {
+        (Some(0), Some(velocity))
+            if velocity.is_stationary() && velocity.period >= MIN_REPRODUCING_PERIOD =>
+        {
+            Some(board::Reproduction::Oscillator)
+        }
+        (Some(0), Some(velocity)) if !velocity.is_stationary() => {
+            Some(board::Reproduction::Spaceship)
+        }
+        _ => None,
+    };
+
+    if let (Some(reproduces_as), Some(period)) = (reproduces_as, period) {
+        println!(
+            "thread {} found a board that comes back to its initial state as a {} of period {}",
+            thread_num,
+            reproduces_as.as_str(),
+            period
+        );
     }
+
+    // Whatever's left on a board that's settled down is its ash
+    let census = match period {
+        Some(_) => Census::take(&game.cells),
+        None => Census::default(),
+    };
+
+    let mut solved = board::Solved {
+        initial: board::Initial {
+            size,
+            cells: board.cells,
+            starting_subdivisions: board.starting_subdivisions,
+            starting_subdiv_utilization: board.starting_subdiv_utilization,
+            seed_region: board.seed_region,
+        },
+        iterations,
+        period,
+        cycle_start,
+        census,
+        stats: game.stats.take(),
+        emissions: emissions.map_or(vec![], |emissions| emissions.emissions),
+        growth,
+        final_population: game.cells.num_living_cells(),
+        peak_population,
+        final_bounding_box: game.cells.bounding_box(),
+        fitness: config.fitness.to_string(),
+        run_id,
+        reproduces_as,
+        origin_similarity: snapshot.origin_similarity(),
+        behavior: None,
+        novelty: None,
+        island: config.islands.then_some(thread_num),
+    };
+
+    if let Some(populations) = populations {
+        solved.behavior = Some(Behavior::describe(&solved, &populations));
+    }
+
+    solved
 }
 
-fn mutate_integer(int: &usize, variation: usize) -> usize {
+fn mutate_integer(int: &usize, variation: usize, rng: &mut impl Rng) -> usize {
     let mut mutated = 0;
-    let addition = thread_rng().gen_range(0..=variation);
-    if thread_rng().gen_bool(0.5) {
-        mutated = int + &addition;
+    let addition = rng.gen_range(0..=variation);
+    if rng.gen_bool(0.5) {
+        mutated = int + addition;
     } else {
         // Gotta make sure not to dip below 0
         if int >= &addition {
-            mutated = int - &addition;
+            mutated = int - addition;
         }
     }
 
     mutated
 }
+
+#[test]
+fn seeded_runs_make_the_same_boards() {
+    let run = || {
+        let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+        let pool = r2d2::Pool::new(manager).unwrap();
+        crate::Db::initialize(pool.get().unwrap());
+        let db = crate::Db::new(pool.get().unwrap());
+
+        let config = Config {
+            seed: Some(42),
+            population_size: 4,
+            ..Config::default()
+        };
+        let population = Arc::new(Population::load(&db, &config.fitness, config.population_size).unwrap());
+        let mut evolver = Evolver::new(24, Arc::clone(&population), config, 1, 0);
+
+        for _ in 0..8 {
+            evolver.evolve_one();
+        }
+
+        population.read(|boards| {
+            boards
+                .iter()
+                .map(|board| {
+                    let initial = &board.solved.initial;
+                    (board.id, initial.cells.clone(), board.solved.iterations)
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn threads_of_different_runs_dont_share_seeds() {
+    assert_ne!(thread_seed(0, 1), thread_seed(1, 0));
+    assert_ne!(thread_seed(7, 0), thread_seed(7, 1));
+}
+
+#[test]
+fn finds_boards_that_reproduce() {
+    let config = Config {
+        mode: Mode::SelfReproducing,
+        ..Config::default()
+    };
+    let reproduces_as = |cells: Vec<(u32, u32)>| {
+        let initial = board::Initial {
+            size: 50,
+            cells,
+            starting_subdivisions: 1,
+            starting_subdiv_utilization: 1,
+            seed_region: genome::central_region(50),
+        };
+        solve(&config, initial, None, 0).reproduces_as
+    };
+
+    // The pulsar is a period 3 oscillator, symmetric across both axes
+    let lines = [0, 5, 7, 12];
+    let spans = [2, 3, 4, 8, 9, 10];
+    let pulsar = lines
+        .iter()
+        .flat_map(|line| spans.iter().flat_map(move |span| [(*line, *span), (*span, *line)]))
+        .map(|(i, j)| (i + 20, j + 20))
+        .collect();
+
+    assert_eq!(
+        reproduces_as(vec![(10, 11), (11, 12), (12, 10), (12, 11), (12, 12)]),
+        Some(board::Reproduction::Spaceship)
+    );
+    assert_eq!(reproduces_as(pulsar), Some(board::Reproduction::Oscillator));
+
+    // Still lifes and blinkers come back too, but that's nothing to look for
+    assert_eq!(reproduces_as(vec![(10, 10), (10, 11), (11, 10), (11, 11)]), None);
+    assert_eq!(reproduces_as(vec![(10, 10), (10, 11), (10, 12)]), None);
+}
+
+#[test]
+fn children_of_parents_that_dont_overlap_have_cells() {
+    let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+    let pool = r2d2::Pool::new(manager).unwrap();
+    crate::Db::initialize(pool.get().unwrap());
+    let db = crate::Db::new(pool.get().unwrap());
+
+    let parent = |id, cells: Vec<(u32, u32)>| board::Saved {
+        id,
+        solved: solve(
+            &Config::default(),
+            board::Initial {
+                size: 50,
+                cells,
+                starting_subdivisions: 1,
+                starting_subdiv_utilization: 1,
+                seed_region: genome::central_region(50),
+            },
+            None,
+            0,
+        ),
+    };
+    let first = parent(1, vec![(5, 5), (5, 6)]);
+    let second = parent(2, vec![(40, 40), (40, 41)]);
+
+    for crossover in [
+        Crossover::RegionSwap,
+        Crossover::Uniform,
+        Crossover::OnePoint,
+    ] {
+        let config = Config {
+            crossover,
+            seed: Some(7),
+            ..Config::default()
+        };
+        let population = Arc::new(Population::load(&db, &config.fitness, config.population_size).unwrap());
+        let mut evolver = Evolver::new(50, population, config, 1, 0);
+
+        for _ in 0..50 {
+            assert!(!evolver.mate(&first, &second).cells.is_empty());
+        }
+    }
+}
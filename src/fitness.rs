@@ -0,0 +1,244 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board;
+
+// Fitness is what evolution is trying to get more of. There are a handful of built in objectives,
+// and any number of them can be combined with weights, like "longevity=1,period=20", which is
+// what this program has always evolved for. That spec is saved with each board, so boards evolved
+// for one thing never get compared against boards evolved for another.
+
+/// The fitness boards were evolved under before it was configurable
+pub const DEFAULT_SPEC: &str = "longevity=1,period=20";
+
+/// Something to measure a solved board by. Higher is fitter.
+pub trait Fitness {
+    fn measure(&self, board: &board::Solved) -> f64;
+}
+
+/// The built in objectives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+    /// How many iterations the board ran for before it died, looped or was found to be growing
+    Longevity,
+
+    /// How many cells the board started with
+    InitialCells,
+
+    /// How long the loop is that the board ended up in, 0 if it didn't
+    Period,
+
+    /// How many generations the board took to settle into its loop, 0 if it never did
+    Stabilization,
+
+    /// How many cells were alive at the end
+    FinalPopulation,
+
+    /// The most cells alive at any one time
+    PeakPopulation,
+
+    /// How many different kinds of objects the board left behind
+    CensusDiversity,
+
+    /// How many times bigger the area around the living cells got from start to finish
+    BoundingBoxGrowth,
+
+    /// 1 if the board made or turned into a spaceship, otherwise 0
+    SpaceshipFound,
+
+    /// For boards that come back to their own initial state, the period they do it in. For the
+    /// rest, somewhere between 0 and 1 depending on how close they got, so any board that
+    /// reproduces beats every board that doesn't. Oscillators only count from period 3 up. Still
+    /// lifes and period 2 oscillators (blinkers, toads, beacons) come straight back too easily,
+    /// and get 0.
+    SelfReproduction,
+
+    /// How different the board's behavior was from everything seen before it, see novelty. 0 for
+    /// boards that weren't evolved in novelty mode.
+    Novelty,
+}
+
+impl Objective {
+    const ALL: [Objective; 11] = [
+        Objective::Longevity,
+        Objective::InitialCells,
+        Objective::Period,
+        Objective::Stabilization,
+        Objective::FinalPopulation,
+        Objective::PeakPopulation,
+        Objective::CensusDiversity,
+        Objective::BoundingBoxGrowth,
+        Objective::SpaceshipFound,
+        Objective::SelfReproduction,
+        Objective::Novelty,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Objective::Longevity => "longevity",
+            Objective::InitialCells => "initial-cells",
+            Objective::Period => "period",
+            Objective::Stabilization => "stabilization",
+            Objective::FinalPopulation => "final-population",
+            Objective::PeakPopulation => "peak-population",
+            Objective::CensusDiversity => "census-diversity",
+            Objective::BoundingBoxGrowth => "bounding-box-growth",
+            Objective::SpaceshipFound => "spaceship-found",
+            Objective::SelfReproduction => "self-reproduction",
+            Objective::Novelty => "novelty",
+        }
+    }
+}
+
+impl Fitness for Objective {
+    fn measure(&self, board: &board::Solved) -> f64 {
+        match self {
+            Objective::Longevity => board.iterations as f64,
+            Objective::InitialCells => board.initial.cells.len() as f64,
+            Objective::Period => board.period.unwrap_or(0) as f64,
+            Objective::Stabilization => board.cycle_start.unwrap_or(0) as f64,
+            Objective::FinalPopulation => board.final_population as f64,
+            Objective::PeakPopulation => board.peak_population as f64,
+            Objective::CensusDiversity => board.census.objects.len() as f64,
+            Objective::BoundingBoxGrowth => {
+                let area = |b: crate::cells::BoundingBox| (b.height * b.width) as f64;
+
+                match (board.initial.bounding_box(), board.final_bounding_box) {
+                    (Some(initial), Some(last)) => area(last) / area(initial),
+                    _ => 0.0,
+                }
+            }
+            Objective::SpaceshipFound => {
+                let in_census = board
+                    .census
+                    .objects
+                    .keys()
+                    .any(|apgcode| apgcode.starts_with("xq"));
+                (in_census || !board.emissions.is_empty()) as u8 as f64
+            }
+            Objective::SelfReproduction => match (board.reproduces_as, board.cycle_start) {
+                (Some(_), _) => board.period.unwrap_or(0) as f64,
+                (None, Some(0)) => 0.0,
+                (None, _) => board.origin_similarity.unwrap_or(0.0),
+            },
+            Objective::Novelty => board.novelty.unwrap_or(0.0),
+        }
+    }
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Objective::ALL
+            .into_iter()
+            .find(|objective| objective.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Objective::ALL.iter().map(|o| o.name()).collect();
+                format!(
+                    "unknown fitness {}, expected one of {}",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// A weighted sum of objectives
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weighted {
+    pub terms: Vec<(Objective, f64)>,
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        DEFAULT_SPEC.parse().unwrap()
+    }
+}
+
+impl Fitness for Weighted {
+    fn measure(&self, board: &board::Solved) -> f64 {
+        self.terms
+            .iter()
+            .map(|(objective, weight)| objective.measure(board) * weight)
+            .sum()
+    }
+}
+
+/// Parses specs like "longevity=1,period=20". A name without a weight gets a weight of 1, and
+/// putting a - in front of a name flips its weight, so "longevity,-initial-cells" is the same as
+/// "longevity=1,initial-cells=-1". Terms are sorted by name, so the order they're written in
+/// doesn't make for a different spec.
+impl FromStr for Weighted {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut terms = s
+            .split(',')
+            .map(|term| {
+                let mut parts = term.trim().splitn(2, '=');
+                let name = parts.next().unwrap();
+                let (name, sign) = match name.strip_prefix('-') {
+                    Some(name) => (name, -1.0),
+                    None => (name, 1.0),
+                };
+
+                let objective: Objective = name.parse()?;
+                let weight: f64 = match parts.next() {
+                    Some(weight) => weight
+                        .parse()
+                        .map_err(|_| format!("bad weight {} for {}", weight, objective.name()))?,
+                    None => 1.0,
+                };
+
+                Ok((objective, weight * sign))
+            })
+            .collect::<Result<Vec<(Objective, f64)>, String>>()?;
+        terms.sort_by_key(|(objective, _)| objective.name());
+
+        Ok(Weighted { terms })
+    }
+}
+
+/// The same spec it was parsed from, give or take whitespace, implicit weights and the order of
+/// the terms
+impl fmt::Display for Weighted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|(objective, weight)| format!("{}={}", objective.name(), weight))
+            .collect();
+
+        write!(f, "{}", terms.join(","))
+    }
+}
+
+/// Whether two specs weigh the same objectives the same, whatever order they're written in. A spec
+/// that doesn't parse, from some older version, is only the same as itself.
+pub fn same_spec(a: &str, b: &str) -> bool {
+    match (a.parse::<Weighted>(), b.parse::<Weighted>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+#[test]
+fn parses_weighted_specs() {
+    let fitness: Weighted = "period=20, longevity".parse().unwrap();
+
+    assert_eq!(
+        fitness.terms,
+        vec![(Objective::Longevity, 1.0), (Objective::Period, 20.0)]
+    );
+    assert_eq!(fitness.to_string(), "longevity=1,period=20");
+    assert_eq!(Weighted::default().to_string(), DEFAULT_SPEC);
+    assert_eq!(
+        "longevity,-initial-cells".parse::<Weighted>().unwrap().to_string(),
+        "initial-cells=-1,longevity=1"
+    );
+    assert!(same_spec("longevity=1,period=20", "period=20, longevity"));
+    assert!(!same_spec("longevity=1,period=20", "longevity=1,period=2"));
+    assert!("longevity=lots".parse::<Weighted>().is_err());
+    assert!("beauty".parse::<Weighted>().is_err());
+}
@@ -1,4 +1,4 @@
-use super::{Cells, Snapshot};
+use super::{Cells, Snapshot, Stats};
 use drawille::Canvas;
 use drawille::PixelColor;
 // use rand::Rng;
@@ -7,26 +7,49 @@ pub struct Game {
     pub snapshot: Option<Snapshot>,
     pub canvas: Option<Canvas>,
     pub cells: Cells,
+    pub stats: Option<Stats>,
     pub iterations: usize,
+
+    /// How many of the iterations went to loading the cells in rather than playing them out
+    loading_iterations: usize,
 }
 
 impl Game {
     /// Implement a new Game object, which orchestrates the conways game of life. Pass in a canvas
     /// and it'll print the game to the screen at every step, and a snapshot and it'll keep track
-    /// of what's gone on.
+    /// of what's gone on. Pass in stats and it'll record a time series of the whole run, starting
+    /// with the cells as they're given.
+    ///
+    /// Cells that were only just born (see Cells::birth_multiple) aren't alive until they're
+    /// committed, which the first step does. That step counts as an iteration, but not as a
+    /// generation, see generation().
     pub fn new(
         snapshot: Option<Snapshot>,
         cells: Cells,
         canvas: Option<Canvas>,
+        mut stats: Option<Stats>,
     ) -> Game {
+        if let Some(stats) = &mut stats {
+            if cells.num_living_cells() > 0 {
+                stats.record(&cells, 0);
+            }
+        }
+
         Game {
             snapshot,
             canvas,
             cells,
+            stats,
             iterations: 0,
+            loading_iterations: 0,
         }
     }
 
+    /// The generation the board's on, counting the cells it started with as generation 0
+    pub fn generation(&self) -> usize {
+        self.iterations - self.loading_iterations
+    }
+
     // Conway's rules:
     // Births: Each dead cell adjacent to exactly three live neighbors will become live in the next generation.
     // Death by isolation: Each live cell with one or fewer live neighbors will die in the next generation.
@@ -37,6 +60,10 @@ impl Game {
     /// dead on the next pass. Paint to the screen, keep track of iteration variable, manage
     /// snapshot.
     pub fn step(&mut self) {
+        // Nothing's alive before the initial cells are committed, so there's nothing to remember
+        // about the state going into this step
+        let loading = self.iterations == 0 && self.cells.num_living_cells() == 0;
+
         self.cells
             .living_cells_and_neighbors()
             .iter()
@@ -87,6 +114,9 @@ impl Game {
         self.cells.commit();
 
         self.iterations += 1;
+        if loading {
+            self.loading_iterations += 1;
+        }
 
         // Draw
         if let Some(canvas) = &mut self.canvas {
@@ -97,7 +127,14 @@ impl Game {
 
         // Keep track
         if let Some(snapshot) = &mut self.snapshot {
-            snapshot.commit_cells();
+            if !loading {
+                snapshot.commit_cells();
+            }
+        }
+
+        let generation = self.generation();
+        if let Some(stats) = &mut self.stats {
+            stats.record(&self.cells, generation);
         }
 
     }
@@ -0,0 +1,384 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+
+use rand::Rng;
+
+use crate::cells::BoundingBox;
+
+// The genome's responsibility is to breed new cell layouts out of old ones. A board's genome is
+// just its initial cells, so a child takes after its parents by actually inheriting their cells,
+// mixed together by crossover and then nudged a little by mutation.
+//
+// Brand new boards don't have parents, so their cells are seeded at random: the seed region is
+// split up into a grid of subdivisions, and the cells are scattered over a few of them picked at
+// random. How many subdivisions there are, how many get used and where the region is all evolve
+// along with the cells, but they only lay out a child's cells the rare times it's reseeded rather
+// than bred (see the evolver's RESEED_RATE). Otherwise they're along for the ride.
+//
+// Boards wrap around at size, so every coordinate here is kept in 0..=size.
+
+/// How often each kind of mutation happens to a child
+const MUTATION_RATE: f64 = 0.25;
+
+/// The most cells a single flip, add or remove mutation touches
+const MAX_MUTATED_CELLS: usize = 5;
+
+/// The furthest a shift mutation moves the whole layout, and the furthest from an existing cell an
+/// added one lands
+const MAX_OFFSET: i64 = 2;
+
+/// The furthest, as a fraction of the board, a seed region's edges move in a single mutation
+const REGION_VARIATION: u32 = 20;
+
+/// The seed region boards had before it could evolve, the 2/5 to 3/5 square in the middle
+pub fn central_region(size: u32) -> BoundingBox {
+    let start = (size * 2) / 5;
+    let end = (size * 3) / 5;
+
+    BoundingBox {
+        top: start,
+        left: start,
+        height: end - start,
+        width: end - start,
+    }
+}
+
+/// A square of the given side in the middle of the board
+pub fn central_square(size: u32, side: u32) -> BoundingBox {
+    let side = side.min(size + 1);
+    let start = (size + 1 - side) / 2;
+
+    BoundingBox {
+        top: start,
+        left: start,
+        height: side,
+        width: side,
+    }
+}
+
+/// Cut a layout down to fit in a square of the given side, and to no more than max_cells cells,
+/// dropping random ones if there are too many. The square goes wherever it holds the most cells,
+/// out of those with a cell in their top left corner, so anything with cells keeps at least one.
+pub fn confine(
+    cells: &[(u32, u32)],
+    side: u32,
+    max_cells: usize,
+    rng: &mut impl Rng,
+) -> Vec<(u32, u32)> {
+    let within = |(top, left): (u32, u32)| {
+        move |(i, j): &&(u32, u32)| (top..top + side).contains(i) && (left..left + side).contains(j)
+    };
+
+    let corner = match cells
+        .iter()
+        .max_by_key(|corner| (cells.iter().filter(within(**corner)).count(), Reverse(**corner)))
+    {
+        Some(corner) => *corner,
+        None => return vec![],
+    };
+
+    let mut cells: Vec<(u32, u32)> = cells.iter().filter(within(corner)).copied().collect();
+
+    while cells.len() > max_cells.max(1) {
+        cells.remove(rng.gen_range(0..cells.len()));
+    }
+
+    cells
+}
+
+/// Scatter this many cells over the seed region. The region's split into a grid of subdivisions,
+/// as close to square as it'll go, and the cells only go into `utilization` of them, picked at
+/// random.
+pub fn seed(
+    region: BoundingBox,
+    subdivisions: usize,
+    utilization: usize,
+    num: usize,
+    rng: &mut impl Rng,
+) -> Vec<(u32, u32)> {
+    let subdivisions = subdivisions.max(1);
+    let utilization = utilization.clamp(1, subdivisions);
+
+    let columns = (subdivisions as f64).sqrt().ceil() as u32;
+    let rows = (subdivisions as u32).div_ceil(columns);
+
+    // Where the nth subdivision starts and how big it is along one side of the region. Tiny
+    // regions can have more subdivisions than cells across, those just get a single cell.
+    let span = |start: u32, length: u32, parts: u32, n: u32| {
+        let from = (start + n * length / parts).min(start + length - 1);
+        let to = start + (n + 1) * length / parts;
+        (from, to.saturating_sub(from).max(1))
+    };
+
+    let used = rand::seq::index::sample(rng, subdivisions, utilization).into_vec();
+
+    let cells: BTreeSet<(u32, u32)> = (0..num)
+        .map(|_| {
+            let subdivision = used[rng.gen_range(0..used.len())] as u32;
+            let (top, height) = span(region.top, region.height, rows, subdivision / columns);
+            let (left, width) = span(region.left, region.width, columns, subdivision % columns);
+
+            (
+                top + rng.gen_range(0..height),
+                left + rng.gen_range(0..width),
+            )
+        })
+        .collect();
+
+    cells.into_iter().collect()
+}
+
+/// Somewhere between the two parents' seed regions
+pub fn blend_regions(first: BoundingBox, second: BoundingBox) -> BoundingBox {
+    BoundingBox {
+        top: (first.top + second.top) / 2,
+        left: (first.left + second.left) / 2,
+        height: (first.height + second.height) / 2,
+        width: (first.width + second.width) / 2,
+    }
+}
+
+/// Move a seed region's edges around a little, keeping it on the board
+pub fn mutate_region(size: u32, region: BoundingBox, rng: &mut impl Rng) -> BoundingBox {
+    let variation = (size / REGION_VARIATION).max(1) as i64;
+    let mut nudge = |n: u32, min: i64, max: i64| {
+        (n as i64 + rng.gen_range(-variation..=variation)).clamp(min, max) as u32
+    };
+
+    let top = nudge(region.top, 0, size as i64);
+    let left = nudge(region.left, 0, size as i64);
+    let height = nudge(region.height, 1, (size + 1 - top) as i64);
+    let width = nudge(region.width, 1, (size + 1 - left) as i64);
+
+    BoundingBox {
+        top,
+        left,
+        height,
+        width,
+    }
+}
+
+/// How two parents' cells get combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Crossover {
+    /// Take a random rectangle of the board from the second parent, and the rest from the first.
+    /// Keeps whole neighborhoods of cells together.
+    #[default]
+    RegionSwap,
+
+    /// Take every spot on the board from one parent or the other at random
+    Uniform,
+
+    /// Read the board row by row, and take everything before a random point from the first parent
+    /// and everything after it from the second
+    OnePoint,
+}
+
+/// Combine two parents' cells into a child's
+pub fn crossover(
+    kind: Crossover,
+    size: u32,
+    first: &[(u32, u32)],
+    second: &[(u32, u32)],
+    rng: &mut impl Rng,
+) -> Vec<(u32, u32)> {
+    let first: BTreeSet<(u32, u32)> = first.iter().copied().collect();
+    let second: BTreeSet<(u32, u32)> = second.iter().copied().collect();
+
+    let child: BTreeSet<(u32, u32)> = match kind {
+        Crossover::RegionSwap => {
+            let top = rng.gen_range(0..=size);
+            let left = rng.gen_range(0..=size);
+            let region = BoundingBox {
+                top,
+                left,
+                height: rng.gen_range(1..=size + 1 - top),
+                width: rng.gen_range(1..=size + 1 - left),
+            };
+            let inside = |(i, j): &(u32, u32)| {
+                (region.top..region.top + region.height).contains(i)
+                    && (region.left..region.left + region.width).contains(j)
+            };
+
+            first
+                .iter()
+                .filter(|cell| !inside(cell))
+                .chain(second.iter().filter(|cell| inside(cell)))
+                .copied()
+                .collect()
+        }
+
+        // Spots both parents agree on stay the way they are, the rest are a coin flip
+        Crossover::Uniform => first
+            .symmetric_difference(&second)
+            .filter(|_| rng.gen_bool(0.5))
+            .chain(first.intersection(&second))
+            .copied()
+            .collect(),
+
+        Crossover::OnePoint => {
+            let width = size as u64 + 1;
+            let order = |(i, j): &(u32, u32)| *i as u64 * width + *j as u64;
+            let point = rng.gen_range(0..width * width);
+
+            first
+                .iter()
+                .filter(|cell| order(cell) < point)
+                .chain(second.iter().filter(|cell| order(cell) >= point))
+                .copied()
+                .collect()
+        }
+    };
+
+    child.into_iter().collect()
+}
+
+/// Maybe flip a few cells around the layout, shift the whole thing over, add cells next to
+/// existing ones or take some away. Each happens independently, so most children come out
+/// untouched or with a single change.
+pub fn mutate(size: u32, cells: &[(u32, u32)], rng: &mut impl Rng) -> Vec<(u32, u32)> {
+    let mut cells: BTreeSet<(u32, u32)> = cells.iter().copied().collect();
+    let width = size as i64 + 1;
+    let wrap = |i: i64| i.rem_euclid(width) as u32;
+
+    // Flip: toggle spots inside the layout's bounding box
+    if rng.gen_bool(MUTATION_RATE) {
+        if let Some(b) = BoundingBox::around(cells.iter()) {
+            for _ in 0..rng.gen_range(1..=MAX_MUTATED_CELLS) {
+                let cell = (
+                    b.top + rng.gen_range(0..b.height),
+                    b.left + rng.gen_range(0..b.width),
+                );
+
+                if !cells.remove(&cell) {
+                    cells.insert(cell);
+                }
+            }
+        }
+    }
+
+    // Shift: move everything over together
+    if rng.gen_bool(MUTATION_RATE) {
+        let di = rng.gen_range(-MAX_OFFSET..=MAX_OFFSET);
+        let dj = rng.gen_range(-MAX_OFFSET..=MAX_OFFSET);
+
+        cells = cells
+            .into_iter()
+            .map(|(i, j)| (wrap(i as i64 + di), wrap(j as i64 + dj)))
+            .collect();
+    }
+
+    // Add: birth cells near ones that are already there
+    if rng.gen_bool(MUTATION_RATE) && !cells.is_empty() {
+        for _ in 0..rng.gen_range(1..=MAX_MUTATED_CELLS) {
+            let (i, j) = *cells.iter().nth(rng.gen_range(0..cells.len())).unwrap();
+            cells.insert((
+                wrap(i as i64 + rng.gen_range(-MAX_OFFSET..=MAX_OFFSET)),
+                wrap(j as i64 + rng.gen_range(-MAX_OFFSET..=MAX_OFFSET)),
+            ));
+        }
+    }
+
+    // Remove: take some away
+    if rng.gen_bool(MUTATION_RATE) {
+        for _ in 0..rng.gen_range(1..=MAX_MUTATED_CELLS) {
+            if cells.is_empty() {
+                break;
+            }
+
+            let cell = *cells.iter().nth(rng.gen_range(0..cells.len())).unwrap();
+            cells.remove(&cell);
+        }
+    }
+
+    cells.into_iter().collect()
+}
+
+/// Toggle a single spot in the layout's bounding box or just around it, so the layout can grow as
+/// well as shrink. An empty layout gets a cell in the middle of the board.
+pub fn flip_cell(size: u32, cells: &[(u32, u32)], rng: &mut impl Rng) -> Vec<(u32, u32)> {
+    let mut cells: BTreeSet<(u32, u32)> = cells.iter().copied().collect();
+    let width = size as i64 + 1;
+    let wrap = |i: i64| i.rem_euclid(width) as u32;
+
+    let cell = match BoundingBox::around(cells.iter()) {
+        Some(b) => (
+            wrap(b.top as i64 + rng.gen_range(-1..=b.height as i64)),
+            wrap(b.left as i64 + rng.gen_range(-1..=b.width as i64)),
+        ),
+        None => (size / 2, size / 2),
+    };
+
+    if !cells.remove(&cell) {
+        cells.insert(cell);
+    }
+
+    cells.into_iter().collect()
+}
+
+#[test]
+fn seeds_only_into_used_subdivisions() {
+    let mut rng = rand::thread_rng();
+    let region = BoundingBox {
+        top: 10,
+        left: 20,
+        height: 40,
+        width: 40,
+    };
+
+    // Four subdivisions make a 2x2 grid of 20x20 squares, and only one of them gets used
+    let cells = seed(region, 4, 1, 100, &mut rng);
+    let quadrant = |(i, j): &(u32, u32)| ((i - 10) / 20, (j - 20) / 20);
+
+    assert!(!cells.is_empty());
+    assert!(cells
+        .iter()
+        .all(|cell| quadrant(cell) == quadrant(&cells[0])));
+    assert!(cells
+        .iter()
+        .all(|(i, j)| (10..50).contains(i) && (20..60).contains(j)));
+}
+
+#[test]
+fn confines_to_a_small_square() {
+    let mut rng = rand::thread_rng();
+    let cells: Vec<(u32, u32)> = (0..20).map(|n| (10 + n, 10 + n % 3)).collect();
+
+    let confined = confine(&cells, 8, 5, &mut rng);
+    let b = BoundingBox::around(confined.iter()).unwrap();
+
+    assert_eq!(confined.len(), 5);
+    assert!(b.height <= 8 && b.width <= 8);
+
+    // Stragglers off to either side don't pull the square away from where the cells are
+    let block = [(30, 30), (30, 31), (31, 30), (31, 31)];
+    let spread: Vec<(u32, u32)> = [(0, 40), (40, 0)].into_iter().chain(block).collect();
+    assert_eq!(confine(&spread, 8, 12, &mut rng), block.to_vec());
+    assert_eq!(confine(&spread, 8, 0, &mut rng).len(), 1);
+}
+
+#[test]
+fn crossover_only_inherits_parent_cells() {
+    let mut rng = rand::thread_rng();
+    let first = [(10, 10), (10, 11), (11, 10), (11, 11)];
+    let second = [(40, 41), (41, 42), (42, 40), (42, 41), (42, 42)];
+
+    for kind in [
+        Crossover::RegionSwap,
+        Crossover::Uniform,
+        Crossover::OnePoint,
+    ] {
+        // Parents that agree have nothing to disagree over
+        assert_eq!(
+            crossover(kind, 60, &first, &first, &mut rng),
+            first.to_vec()
+        );
+
+        for _ in 0..20 {
+            let child = crossover(kind, 60, &first, &second, &mut rng);
+            assert!(child
+                .iter()
+                .all(|cell| first.contains(cell) || second.contains(cell)));
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+use crate::cells::Connectivity;
+use crate::Cells;
+
+// The growth detector's responsibility is to notice boards that are never going to repeat b/c they
+// keep growing, like guns (which keep sending out gliders) and puffers (which leave a trail of
+// debris behind them). The snapshot can't ever catch these, since no state comes around twice.
+//
+// What these have in common is that their population, looked at once a period, goes up by the
+// same amount every time. So we keep the last stretch of populations, and look for a period over
+// which the population's change is the same, and positive, all the way back.
+//
+// A population count can line up like that by chance though, so that's only a candidate. What
+// really makes a gun or a puffer is that it does the same thing every period, so the board's
+// structure has to agree: the number of separate clusters on it, counted once a period for a few
+// more periods, has to change by the same amount each time too. Counting clusters is a lot more
+// work than counting cells, which is why it waits for the population to point somewhere.
+
+/// The longest period we look for
+const MAX_PERIOD: usize = 120;
+
+/// How many periods in a row the population has to grow the same way
+const REPEATS: usize = 8;
+
+/// Small periods still have to hold up for at least this many generations, so chaos can't pass for
+/// growth by chance
+const MIN_SPAN: usize = 96;
+
+/// How often to actually look, the checks aren't free
+const CHECK_INTERVAL: usize = 32;
+
+/// How many more periods a candidate's clusters are counted over before it's confirmed
+const CONFIRMATIONS: usize = 3;
+
+/// A pattern whose population goes up by the same amount every period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinearGrowth {
+    pub period: usize,
+
+    /// How many more cells there are after each period
+    pub growth_per_period: usize,
+}
+
+impl LinearGrowth {
+    /// Cells per generation
+    pub fn rate(&self) -> f64 {
+        self.growth_per_period as f64 / self.period as f64
+    }
+}
+
+pub struct GrowthDetector {
+    populations: VecDeque<usize>,
+    recorded: usize,
+    candidate: Option<Candidate>,
+    confirmed: Option<LinearGrowth>,
+}
+
+/// Growth the population points to, waiting on the board's structure to back it up
+struct Candidate {
+    growth: LinearGrowth,
+
+    /// How many clusters the board had, once a period since the candidate came up
+    clusters: Vec<usize>,
+
+    /// How many generations will have been recorded when the clusters are next counted
+    next_count: usize,
+}
+
+impl Default for GrowthDetector {
+    fn default() -> Self {
+        GrowthDetector::new()
+    }
+}
+
+impl GrowthDetector {
+    pub fn new() -> GrowthDetector {
+        GrowthDetector {
+            populations: VecDeque::new(),
+            recorded: 0,
+            candidate: None,
+            confirmed: None,
+        }
+    }
+
+    /// Add the next generation
+    pub fn record(&mut self, cells: &Cells) {
+        self.populations.push_back(cells.num_living_cells());
+        self.recorded += 1;
+
+        if self.populations.len() > MAX_PERIOD * (REPEATS + 1) {
+            self.populations.pop_front();
+        }
+
+        if self.confirmed.is_some() {
+            return;
+        }
+
+        match self.candidate.take() {
+            Some(candidate) => self.confirm(candidate, cells),
+
+            // The smallest period that works, any multiple of it would too
+            None if self.recorded.is_multiple_of(CHECK_INTERVAL) => {
+                if let Some(growth) = (1..=MAX_PERIOD).find_map(|period| self.growth_over(period)) {
+                    self.candidate = Some(Candidate {
+                        growth,
+                        clusters: vec![count_clusters(cells)],
+                        next_count: self.recorded + growth.period,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Whether the board's been found to be growing linearly, as of the last generation recorded
+    pub fn detect(&self) -> Option<LinearGrowth> {
+        self.confirmed
+    }
+
+    /// Count the candidate's clusters if it's been another period, and once it's been enough of
+    /// them, confirm it if both the population and the clusters kept it up. Otherwise it's
+    /// dropped, and the population gets looked at afresh.
+    fn confirm(&mut self, mut candidate: Candidate, cells: &Cells) {
+        if self.recorded < candidate.next_count {
+            self.candidate = Some(candidate);
+            return;
+        }
+
+        candidate.clusters.push(count_clusters(cells));
+        candidate.next_count += candidate.growth.period;
+
+        if candidate.clusters.len() <= CONFIRMATIONS {
+            self.candidate = Some(candidate);
+            return;
+        }
+
+        let changes: Vec<i64> = candidate
+            .clusters
+            .windows(2)
+            .map(|pair| pair[1] as i64 - pair[0] as i64)
+            .collect();
+        let steady = changes.iter().all(|change| *change == changes[0]);
+        let still_growing = self.growth_over(candidate.growth.period) == Some(candidate.growth);
+
+        if steady && still_growing {
+            self.confirmed = Some(candidate.growth);
+        }
+    }
+
+    fn growth_over(&self, period: usize) -> Option<LinearGrowth> {
+        let span = (period * REPEATS).max(MIN_SPAN);
+
+        if self.populations.len() < span + period {
+            return None;
+        }
+
+        let last = self.populations.len() - 1;
+        let growth_per_period =
+            self.populations[last].checked_sub(self.populations[last - period])?;
+
+        if growth_per_period == 0 {
+            return None;
+        }
+
+        let holds = (last - span + 1..=last).all(|t| {
+            self.populations[t] == self.populations[t - period] + growth_per_period
+        });
+
+        holds.then_some(LinearGrowth {
+            period,
+            growth_per_period,
+        })
+    }
+}
+
+/// Separate groups of touching cells
+fn count_clusters(cells: &Cells) -> usize {
+    cells.clusters(Connectivity::Moore, 1).len()
+}
+
+#[test]
+fn detects_glider_gun() {
+    let gosper_glider_gun = [
+        (0, 24),
+        (1, 22), (1, 24),
+        (2, 12), (2, 13), (2, 20), (2, 21), (2, 34), (2, 35),
+        (3, 11), (3, 15), (3, 20), (3, 21), (3, 34), (3, 35),
+        (4, 0), (4, 1), (4, 10), (4, 16), (4, 20), (4, 21),
+        (5, 0), (5, 1), (5, 10), (5, 14), (5, 16), (5, 17), (5, 22), (5, 24),
+        (6, 10), (6, 16), (6, 24),
+        (7, 11), (7, 15),
+        (8, 12), (8, 13),
+    ];
+
+    let mut cells = crate::Cells::new(200);
+    cells.birth_multiple(&gosper_glider_gun.map(|(i, j)| (i + 10, j + 10)));
+    let mut game = crate::Game::new(None, cells, None, None);
+    let mut detector = GrowthDetector::new();
+
+    let growth = loop {
+        game.step();
+        detector.record(&game.cells);
+
+        if let Some(growth) = detector.detect() {
+            break growth;
+        }
+
+        assert!(game.iterations < 600, "never noticed the gun growing");
+    };
+
+    assert_eq!(growth, LinearGrowth { period: 30, growth_per_period: 5 });
+}
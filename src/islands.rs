@@ -0,0 +1,54 @@
+use std::sync::Arc;
+
+use crate::fitness::Weighted;
+use crate::Population;
+
+// The island model splits evolution up so each thread works on a population of its own, an
+// island, instead of all of them crowding into one. Islands drift off in their own directions,
+// which keeps more variety around than a single population converging on one answer. Every so
+// often each island sends copies of its best boards off to its neighbors, so a good idea found
+// on one island gets a chance to spread.
+//
+// Islands can each evolve for something different, in which case migrants get measured by their
+// new island's fitness once they land.
+
+/// Which islands an island's migrants go to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Topology {
+    /// Each island only sends to the next one over, with the last sending to the first
+    #[default]
+    Ring,
+
+    /// Each island sends to every other island
+    All,
+}
+
+impl Topology {
+    /// The islands the given island sends its migrants to, out of this many islands
+    pub fn neighbors(&self, island: u32, islands: u32) -> Vec<u32> {
+        match self {
+            Topology::Ring if islands > 1 => vec![(island + 1) % islands],
+            Topology::Ring => vec![],
+            Topology::All => (0..islands).filter(|other| *other != island).collect(),
+        }
+    }
+}
+
+/// Somewhere migrants can go
+#[derive(Clone)]
+pub struct Island {
+    pub id: u32,
+
+    /// What the island evolves for, which migrants are measured by once they get there
+    pub fitness: Weighted,
+
+    pub population: Arc<Population>,
+}
+
+#[test]
+fn finds_neighboring_islands() {
+    assert_eq!(Topology::Ring.neighbors(0, 4), vec![1]);
+    assert_eq!(Topology::Ring.neighbors(3, 4), vec![0]);
+    assert_eq!(Topology::Ring.neighbors(0, 1), Vec::<u32>::new());
+    assert_eq!(Topology::All.neighbors(2, 4), vec![0, 1, 3]);
+}
@@ -5,6 +5,20 @@ pub mod evolver;
 pub mod db;
 pub mod board;
 pub mod cli;
+pub mod census;
+pub mod stats;
+pub mod emissions;
+pub mod growth;
+pub mod fitness;
+pub mod genome;
+pub mod selection;
+pub mod population;
+pub mod novelty;
+pub mod map_elites;
+pub mod pareto;
+pub mod islands;
+pub mod optimizer;
+pub mod search;
 
 pub use cells::Cells;
 pub use snapshot::Snapshot;
@@ -12,3 +26,6 @@ pub use game::Game;
 pub use evolver::Evolver;
 pub use db::Db;
 pub use cli::{Args, Commands};
+pub use census::Census;
+pub use stats::Stats;
+pub use population::Population;
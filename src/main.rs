@@ -1,9 +1,13 @@
 extern crate drawille;
 
-use clap::Parser;
-use conway::{Args, Commands, Db, Evolver};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use conway::fitness::{self, Fitness};
+use conway::islands::Island;
+use conway::{evolver, Args, Commands, Db, Evolver, Population};
 use core::time;
 use r2d2::PooledConnection;
+use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 
 extern crate r2d2;
@@ -25,51 +29,358 @@ fn main() {
     Db::initialize(pool.get().unwrap());
 
     match &args.command {
-        Commands::Evolve { threads } => evolve(*threads, pool.clone(), size),
+        Commands::Evolve {
+            threads,
+            symmetry,
+            cycle_detection,
+            record_stats,
+            detect_emissions,
+            remove_emissions,
+            mode,
+            fitness,
+            crossover,
+            selection,
+            population_size,
+            elites,
+            replacement,
+            seed,
+            descriptors,
+            islands,
+            migration_interval,
+            migrants,
+            topology,
+            island_fitness,
+        } => {
+            if elites >= population_size {
+                Args::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "--elites has to be less than --population-size",
+                    )
+                    .exit();
+            }
+            if *islands && matches!(mode, evolver::Mode::MapElites | evolver::Mode::Pareto) {
+                Args::command()
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        "--islands doesn't work with map-elites or pareto mode",
+                    )
+                    .exit();
+            }
+
+            let seed = seed.unwrap_or_else(rand::random);
+            let config = evolver::Config {
+                symmetry: *symmetry,
+                cycle_detection: *cycle_detection,
+                record_stats: *record_stats,
+                detect_emissions: *detect_emissions,
+                remove_emissions: *remove_emissions,
+                fitness: fitness.clone().unwrap_or_else(|| mode.default_fitness()),
+                crossover: *crossover,
+                selection: *selection,
+                population_size: *population_size,
+                elites: *elites,
+                replacement: *replacement,
+                seed: Some(seed),
+                mode: *mode,
+                descriptors: *descriptors,
+                islands: *islands,
+                migration_interval: *migration_interval,
+                migrants: *migrants,
+                topology: *topology,
+                island_fitnesses: island_fitness.clone(),
+            };
+
+            let run_id = Db::new(pool.get().unwrap()).save_run(&config).unwrap();
+            println!("starting run {} with seed {}", run_id, seed);
+
+            evolve(*threads, pool.clone(), size, config, run_id)
+        }
 
         Commands::Display { delay } => {
             display(*delay, pool.clone().get().unwrap());
         }
+
+        Commands::Optimize {
+            id,
+            method,
+            steps,
+            fitness,
+            temperature,
+            cooling,
+            seed,
+        } => {
+            let mut db = Db::new(pool.get().unwrap());
+            let board = db.load_board(*id).expect(
+                "There was an issue loading the board, are you sure you input the numerical id correctly?",
+            );
+
+            // Boards get run the way their run ran them, so they're measured the same way too
+            let run = board
+                .solved
+                .run_id
+                .and_then(|run_id| db.load_run(run_id).ok())
+                .unwrap_or_default();
+            let fitness = fitness.clone().unwrap_or_else(|| {
+                board.solved.fitness.parse().unwrap_or_else(|error| {
+                    Args::command()
+                        .error(
+                            ErrorKind::InvalidValue,
+                            format!("{}, pick one with --fitness", error),
+                        )
+                        .exit()
+                })
+            });
+            let config = evolver::Config {
+                fitness,
+                seed: *seed,
+                // There's a single board here, not a population to split up
+                islands: false,
+                ..run
+            };
+
+            // Novelty's measured against the rest of the population the board came from
+            let neighbors = db
+                .load_boards_evolved_under(&board.solved.fitness, board.solved.island)
+                .unwrap()
+                .into_iter()
+                .filter(|neighbor| neighbor.id != board.id)
+                .filter_map(|neighbor| neighbor.solved.behavior)
+                .collect();
+
+            let mut optimizer = conway::optimizer::Optimizer::new(config.clone(), *method, *steps)
+                .with_schedule(*temperature, *cooling)
+                .with_neighbors(neighbors);
+
+            // The board as stored might have been measured under something else, so it's run
+            // again to get a fair starting point
+            let before = config
+                .fitness
+                .measure(&optimizer.solve(board.solved.initial.clone()));
+            let best = optimizer.optimize(board.solved.initial);
+            let after = config.fitness.measure(&best);
+
+            if after > before {
+                let id = db.save_board(&best).unwrap();
+                println!(
+                    "saved board {}, for a fitness of {} up from {}",
+                    id, after, before
+                );
+            } else {
+                println!("couldn't beat the board's fitness of {}", before);
+            }
+        }
+
+        Commands::Search {
+            width,
+            height,
+            longest,
+            period,
+            spaceships,
+        } => {
+            if width > &size || height > &size {
+                Args::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!("the box has to fit on the {}x{} board", size, size),
+                    )
+                    .exit();
+            }
+            if width * height > conway::search::MAX_CELLS {
+                Args::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!(
+                            "a {}x{} box has 2^{} patterns to try, keep it to {} cells or fewer",
+                            width,
+                            height,
+                            width * height,
+                            conway::search::MAX_CELLS
+                        ),
+                    )
+                    .exit();
+            }
+
+            let criteria = conway::search::Criteria {
+                longest: *longest,
+                periods: period.clone(),
+                spaceships: *spaceships,
+            };
+
+            // Emitted spaceships only count if they're looked for
+            let config = evolver::Config {
+                fitness: "longevity".parse().unwrap(),
+                detect_emissions: true,
+                ..evolver::Config::default()
+            };
+
+            let finds = conway::search::search(size, *width, *height, &criteria, &config);
+
+            let mut db = Db::new(pool.get().unwrap());
+            db.delete_finds(*width, *height).unwrap();
+            for find in &finds {
+                db.save_find(*width, *height, find).unwrap();
+            }
+
+            println!(
+                "saved {} patterns found searching every {}x{} pattern",
+                finds.len(),
+                width,
+                height
+            );
+        }
+
+        Commands::Stats { id } => stats(*id, pool.clone().get().unwrap()),
     }
 }
 
-// Spawn a new evolution for this many threads
-fn evolve(threads: usize, pool: r2d2::Pool<SqliteConnectionManager>, size: u32) {
-    (0..=threads)
+/// How often the population's changes get written to the database
+const FLUSH_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+// Spawn a new evolution for this many threads, all working on the same population or each on an
+// island of its own, and keep the database up to date with them from this one. Evolvers only stop
+// if something's gone wrong, in which case whatever they've found is flushed before going down
+// with them.
+fn evolve(
+    threads: usize,
+    pool: r2d2::Pool<SqliteConnectionManager>,
+    size: u32,
+    config: evolver::Config,
+    run_id: i64,
+) {
+    let mut db = Db::new(pool.get().unwrap());
+    let fitness = config.fitness.to_string();
+
+    let populations: Vec<Arc<Population>> = match (config.islands, config.mode) {
+        (true, _) => {
+            let fitnesses: Vec<fitness::Weighted> = (0..threads as u32)
+                .map(|island| config.island_fitness(island))
+                .collect();
+
+            Population::load_islands(&db, &fitnesses, config.population_size)
+                .unwrap()
+                .into_iter()
+                .map(Arc::new)
+                .collect()
+        }
+        (false, evolver::Mode::MapElites) => vec![Arc::new(
+            Population::load_elites(&db, &fitness, config.descriptors).unwrap(),
+        )],
+        (false, _) => vec![Arc::new(
+            Population::load(&db, &config.fitness, config.population_size).unwrap(),
+        )],
+    };
+
+    let workers: Vec<JoinHandle<()>> = (0..threads)
         .map(|thread_num| {
-            let pool = pool.clone();
-            let db = Db::new(pool.get().unwrap());
+            let population = Arc::clone(&populations[thread_num % populations.len()]);
+            let mut config = config.clone();
+            let mut neighbors = vec![];
+
+            if config.islands {
+                let island = thread_num as u32;
+                config.fitness = config.island_fitness(island);
+
+                neighbors = config
+                    .topology
+                    .neighbors(island, threads as u32)
+                    .into_iter()
+                    .map(|neighbor| Island {
+                        id: neighbor,
+                        fitness: config.island_fitness(neighbor),
+                        population: Arc::clone(&populations[neighbor as usize]),
+                    })
+                    .collect();
+            }
 
             thread::spawn(move || {
-                let mut evolution = Evolver::new(size, db);
-                evolution.begin_evolving(thread_num as u32);
+                let mut evolution =
+                    Evolver::new(size, population, config, run_id, thread_num as u32)
+                        .with_neighbors(neighbors);
+                evolution.begin_evolving();
             })
         })
-        .collect::<Vec<JoinHandle<()>>>()
+        .collect();
+
+    let flush = |db: &mut Db| {
+        for population in &populations {
+            // Whatever didn't make it in stays queued for next time
+            if let Err(error) = population.flush(db) {
+                println!("couldn't bring the database up to date, will try again: {}", error);
+            }
+        }
+    };
+
+    while !workers.iter().any(JoinHandle::is_finished) {
+        thread::sleep(FLUSH_INTERVAL);
+        flush(&mut db);
+    }
+
+    flush(&mut db);
+
+    workers
         .into_iter()
-        .map(thread::JoinHandle::join)
-        .collect::<Result<(), _>>()
+        .filter(JoinHandle::is_finished)
+        .try_for_each(JoinHandle::join)
         .unwrap();
 }
 
 fn display(delay: usize, connection: PooledConnection<SqliteConnectionManager>) {
     let db = Db::new(connection);
 
-    // Load all the boards
-    let mut boards = db.load_boards().unwrap();
+    let finds = db.load_finds().unwrap();
+
+    // Load all the boards, along with what they were evolved for and how fit they are under it.
+    // A spec from an older version that doesn't parse any more can't be measured.
+    let mut boards: Vec<(conway::board::Saved, String, Option<f64>)> = db
+        .load_boards()
+        .unwrap()
+        .into_iter()
+        .map(|board| match board.solved.fitness.parse::<fitness::Weighted>() {
+            Ok(fitness) => {
+                let measured = fitness.measure(&board.solved);
+                (board, fitness.to_string(), Some(measured))
+            }
+            Err(_) => {
+                let spec = board.solved.fitness.clone();
+                (board, spec, None)
+            }
+        })
+        .collect();
 
-    // Sort em up for easier picking
-    boards.sort_by_key(|b| {
-        Evolver::measure_fitness_saved(&b)
+    // Sort em up for easier picking, grouped by what they were evolved for since fitnesses under
+    // different specs can't be compared
+    boards.sort_by(|(_, a_spec, a_fitness), (_, b_spec, b_fitness)| {
+        a_spec.cmp(b_spec).then(
+            a_fitness
+                .unwrap_or(f64::NEG_INFINITY)
+                .total_cmp(&b_fitness.unwrap_or(f64::NEG_INFINITY)),
+        )
     });
 
     // List all the boards
-    for board in boards {
+    let mut current_spec = None;
+    for (board, spec, measured) in boards {
+        if current_spec.as_ref() != Some(&spec) {
+            println!("\nEvolved for {}:", spec);
+            current_spec = Some(spec);
+        }
+
         if let Some(period) = board.solved.period {
             // TODO can we just print the whole board SANS the cells?
+            let stabilized = match board.solved.cycle_start {
+                Some(cycle_start) => format!("stabilized at generation {}", cycle_start),
+                None => String::from("stabilization unknown"),
+            };
+
+            println!(
+                "id: {} || Period {} with {} unique iterations || {}",
+                board.id, period, board.solved.iterations, stabilized
+            );
+        } else if let Some(growth) = board.solved.growth {
             println!(
-                "id: {} || Period {} with {} unique iterations",
-                board.id, period, board.solved.iterations
+                "id: {} || Grows by {} cells every {} generations (gun or puffer) || stopped after {} iterations",
+                board.id, growth.growth_per_period, growth.period, board.solved.iterations
             );
         } else {
             println!(
@@ -77,6 +388,65 @@ fn display(delay: usize, connection: PooledConnection<SqliteConnectionManager>)
                 board.id, board.solved.iterations
             );
         }
+
+        match measured {
+            Some(measured) => println!("    fitness: {}", measured),
+            None => println!("    fitness: unknown, the spec isn't one this version understands"),
+        }
+
+        if let Some(island) = board.solved.island {
+            println!("    evolved on island {}", island);
+        }
+
+        if let Some((width, height, reasons)) = finds.get(&board.id) {
+            let reasons: Vec<&str> = reasons.iter().map(|reason| reason.as_str()).collect();
+            println!(
+                "    found searching every {}x{} pattern, as {}",
+                width,
+                height,
+                reasons.join(" and ")
+            );
+        }
+
+        if let Some(reproduction) = board.solved.reproduces_as {
+            println!(
+                "    comes back to its initial state as a {}",
+                reproduction.as_str()
+            );
+        }
+
+        if !board.solved.census.is_empty() {
+            println!("    census: {}", board.solved.census);
+        }
+
+        if let Some(first) = board.solved.emissions.first() {
+            println!(
+                "    emitted {} spaceships, the first at generation {}: {}",
+                board.solved.emissions.len(),
+                first.generation,
+                describe_emissions(&board.solved.emissions)
+            );
+        }
+    }
+
+    // And every grid of elites, so the odd ones out are easy to find
+    for grid in db.load_grids().unwrap() {
+        println!(
+            "\nElites evolved for {}, by {} and {}:\n",
+            grid.fitness,
+            grid.descriptors.x.name(),
+            grid.descriptors.y.name()
+        );
+        print!("{}", grid);
+    }
+
+    // And the trade-offs pareto runs have found
+    for front in db.load_fronts().unwrap() {
+        println!(
+            "\nPareto front of run {}, evolved for {}:\n",
+            front.run_id, front.fitness
+        );
+        print!("{}", front);
     }
 
     // Ask for which they want
@@ -102,7 +472,7 @@ fn display(delay: usize, connection: PooledConnection<SqliteConnectionManager>)
     let mut cells = conway::Cells::new(board.solved.initial.size);
     cells.birth_multiple(&board.solved.initial.cells);
     let canvas = Some(drawille::Canvas::new(board.solved.initial.size, board.solved.initial.size));
-    let mut game = conway::Game::new(None, cells, canvas);
+    let mut game = conway::Game::new(None, cells, canvas, None);
 
     // Run the game
     loop {
@@ -119,3 +489,50 @@ fn display(delay: usize, connection: PooledConnection<SqliteConnectionManager>)
         thread::sleep(time::Duration::from_millis(delay as u64));
     }
 }
+
+/// Counts of each kind of ship and the way it went, like "3 glider south-east, 1 xq4_6frc west"
+fn describe_emissions(emissions: &[conway::emissions::Emission]) -> String {
+    let mut counts = std::collections::BTreeMap::new();
+
+    for emission in emissions {
+        *counts
+            .entry((emission.apgcode.as_str(), emission.direction))
+            .or_insert(0) += 1;
+    }
+
+    counts
+        .iter()
+        .map(|((apgcode, direction), count)| {
+            let name = conway::census::name(apgcode).unwrap_or(apgcode);
+            format!("{} {} {}", count, name, direction)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn stats(board_id: i64, connection: PooledConnection<SqliteConnectionManager>) {
+    let db = Db::new(connection);
+
+    let board = db.load_board(board_id).expect(
+        "There was an issue loading the board, are you sure you input the numerical id correctly?",
+    );
+
+    let stats = match db.load_stats(board_id).unwrap() {
+        Some(stats) => stats,
+
+        // Not recorded during evolution, so run it again for as long as it ran then
+        None => {
+            let mut cells = conway::Cells::new(board.solved.initial.size);
+            cells.birth_multiple(&board.solved.initial.cells);
+            let mut game = conway::Game::new(None, cells, None, Some(conway::Stats::new()));
+
+            while game.iterations < board.solved.iterations {
+                game.step();
+            }
+
+            game.stats.unwrap()
+        }
+    };
+
+    print!("{}", stats);
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::board;
+use crate::fitness::{Fitness, Objective};
+
+// MAP-Elites keeps the fittest board of every kind rather than the fittest boards overall. Two
+// descriptors, like how many cells a board starts with and what period it ends up in, lay the
+// boards out on a grid, and each spot on the grid holds on to the fittest board that's landed
+// there. A new board only has to beat the one board most like it, so odd boards that would never
+// make a top ten list get kept around and bred from.
+//
+// Descriptors are binned by powers of two, so the grid doesn't need to know how big any of them
+// get, and a board with 1000 cells isn't kept apart from one with 1001.
+
+/// The two things boards get laid out on the grid by. Any objective works as a descriptor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Descriptors {
+    /// Across the grid
+    pub x: Objective,
+
+    /// Down the grid
+    pub y: Objective,
+}
+
+impl Default for Descriptors {
+    fn default() -> Self {
+        Descriptors {
+            x: Objective::InitialCells,
+            y: Objective::Period,
+        }
+    }
+}
+
+impl Descriptors {
+    /// The spot on the grid the board belongs in
+    pub fn cell(&self, board: &board::Solved) -> (u32, u32) {
+        (bin(self.x.measure(board)), bin(self.y.measure(board)))
+    }
+}
+
+/// Which power of two the value falls under. Anything below 1 goes in bin 0, 1 in bin 1, 2 and 3
+/// in bin 2, 4 through 7 in bin 3, and so on.
+pub fn bin(value: f64) -> u32 {
+    match value < 1.0 {
+        true => 0,
+        false => value.log2().floor() as u32 + 1,
+    }
+}
+
+/// The smallest value that goes in the bin
+fn bin_start(bin: u32) -> u64 {
+    match bin {
+        0 => 0,
+        _ => 1 << (bin - 1).min(63),
+    }
+}
+
+/// Parses specs like "initial-cells,period", across then down
+impl FromStr for Descriptors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split(',').map(str::trim).collect::<Vec<&str>>()[..] {
+            [x, y] => Ok(Descriptors {
+                x: x.parse()?,
+                y: y.parse()?,
+            }),
+            _ => Err(format!(
+                "expected two descriptors like initial-cells,period, got {}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Descriptors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.x.name(), self.y.name())
+    }
+}
+
+/// The elites of a single run of MAP-Elites, as saved in the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    /// The spec of the fitness the elites beat everyone else in their spot under
+    pub fitness: String,
+
+    pub descriptors: Descriptors,
+
+    /// The id of the elite at each spot, by its x and y bins
+    pub elites: BTreeMap<(u32, u32), i64>,
+}
+
+/// A table of board ids, with the y descriptor's bins down the side and the x descriptor's across
+/// the top. Each bin is labeled with the smallest value that goes in it.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let max_x = self.elites.keys().map(|(x, _)| *x).max().unwrap_or(0);
+        let max_y = self.elites.keys().map(|(_, y)| *y).max().unwrap_or(0);
+
+        let y_name = self.descriptors.y.name();
+        let width = self
+            .elites
+            .values()
+            .map(|id| id.to_string().len())
+            .chain((0..=max_x).map(|x| bin_start(x).to_string().len()))
+            .max()
+            .unwrap_or(1)
+            + 1;
+
+        writeln!(
+            f,
+            "{:>w$} {}",
+            "",
+            self.descriptors.x.name(),
+            w = y_name.len()
+        )?;
+
+        write!(f, "{}", y_name)?;
+        for x in 0..=max_x {
+            write!(f, "{:>width$}", bin_start(x))?;
+        }
+        writeln!(f)?;
+
+        for y in 0..=max_y {
+            write!(f, "{:>w$}", bin_start(y), w = y_name.len())?;
+            for x in 0..=max_x {
+                match self.elites.get(&(x, y)) {
+                    Some(id) => write!(f, "{:>width$}", id)?,
+                    None => write!(f, "{:>width$}", ".")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn lays_elites_out_by_powers_of_two() {
+    assert_eq!(
+        [0.0, 0.5, 1.0, 2.0, 3.0, 4.0, 7.9, 8.0, 1000.0].map(bin),
+        [0, 0, 1, 2, 2, 3, 3, 4, 10]
+    );
+
+    let descriptors: Descriptors = "initial-cells,period".parse().unwrap();
+    assert_eq!(descriptors, Descriptors::default());
+    assert_eq!(descriptors.to_string(), "initial-cells,period");
+    assert!("initial-cells".parse::<Descriptors>().is_err());
+
+    let grid = Grid {
+        fitness: String::from(crate::fitness::DEFAULT_SPEC),
+        descriptors,
+        elites: [((3, 0), 12), ((1, 2), 7)].into_iter().collect(),
+    };
+
+    assert_eq!(
+        grid.to_string(),
+        "       initial-cells\n\
+         period  0  1  2  4\n     \
+         0  .  .  . 12\n     \
+         1  .  .  .  .\n     \
+         2  .  7  .  .\n"
+    );
+}
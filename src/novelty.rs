@@ -0,0 +1,190 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+
+use crate::board;
+use crate::fitness::{Fitness, Objective};
+
+// Novelty search rewards boards for doing something different rather than for doing one thing
+// well. Every solved board gets a behavior, a handful of numbers describing how it played out, and
+// its novelty is how far that is from the behaviors of its nearest neighbors. The neighbors come
+// from the current population and from an archive of behaviors seen before, which a random few of
+// the boards join, so that going back to old behaviors stops paying off.
+//
+// Novelty is scored when a board is solved, against what's been seen up to then. Whenever the
+// archive grows, what counts as novel shifts, so the boards already in the population get scored
+// again against the new archive and each other.
+
+/// How many points along the population curve go into a behavior
+pub const CURVE_SAMPLES: usize = 8;
+
+/// How many of the nearest behaviors a board's novelty is measured against
+const NEAREST: usize = 15;
+
+/// The chance of any one behavior joining the archive
+const ARCHIVE_RATE: f64 = 0.1;
+
+/// How a board played out, as numbers on roughly the same scale so they can be compared by
+/// distance. Counts go in as log2, so doubling something counts for about as much as going from
+/// empty to a single cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Behavior(pub Vec<f64>);
+
+impl Behavior {
+    /// Describe a board by how long it ran, the loop it fell into, its population and spread
+    /// at the end, what it left behind, and the shape of its population curve. populations is the
+    /// population of every generation, in order.
+    pub fn describe(board: &board::Solved, populations: &[usize]) -> Behavior {
+        let log = |n: f64| (n + 1.0).log2();
+
+        let mut features = vec![
+            log(board.iterations as f64),
+            log(board.period.unwrap_or(0) as f64),
+            log(board.final_population as f64),
+            log(board.peak_population as f64),
+            log(Objective::BoundingBoxGrowth.measure(board)),
+            log(Objective::CensusDiversity.measure(board)),
+        ];
+
+        // Populations as a fraction of the peak, so this is only about the curve's shape
+        let peak = populations.iter().copied().max().unwrap_or(0).max(1) as f64;
+        features.extend((0..CURVE_SAMPLES).map(|sample| match populations.len() {
+            0 => 0.0,
+            len => populations[sample * (len - 1) / (CURVE_SAMPLES - 1)] as f64 / peak,
+        }));
+
+        Behavior(features)
+    }
+
+    pub fn distance(&self, other: &Behavior) -> f64 {
+        self.0
+            .iter()
+            .zip(&other.0)
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// Comma separated, for the database
+impl fmt::Display for Behavior {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let features: Vec<String> = self.0.iter().map(|feature| feature.to_string()).collect();
+        write!(f, "{}", features.join(","))
+    }
+}
+
+impl FromStr for Behavior {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|feature| {
+                feature
+                    .parse()
+                    .map_err(|_| format!("bad behavior feature {}", feature))
+            })
+            .collect::<Result<Vec<f64>, String>>()
+            .map(Behavior)
+    }
+}
+
+/// Behaviors seen before
+#[derive(Debug, Clone, Default)]
+pub struct Archive {
+    behaviors: Vec<Behavior>,
+}
+
+impl Archive {
+    pub fn len(&self) -> usize {
+        self.behaviors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviors.is_empty()
+    }
+
+    /// The average distance from the behavior to its nearest neighbors among the archive and the
+    /// others given. With nothing to compare to, there's no telling how novel it is, so it's 0.
+    pub fn novelty<'a>(
+        &self,
+        behavior: &Behavior,
+        others: impl Iterator<Item = &'a Behavior>,
+    ) -> f64 {
+        let distances = self
+            .behaviors
+            .iter()
+            .map(|other| behavior.distance(other))
+            .chain(others.map(|other| behavior.distance(other)))
+            .collect();
+
+        average_of_nearest(distances)
+    }
+
+    /// Like novelty, for a behavior that might have joined the archive itself. Its own copy there
+    /// isn't a neighbor, so it's left out.
+    pub fn novelty_of_member<'a>(
+        &self,
+        behavior: &Behavior,
+        others: impl Iterator<Item = &'a Behavior>,
+    ) -> f64 {
+        let own = self.behaviors.iter().position(|other| other == behavior);
+
+        let distances = self
+            .behaviors
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| Some(*index) != own)
+            .map(|(_, other)| behavior.distance(other))
+            .chain(others.map(|other| behavior.distance(other)))
+            .collect();
+
+        average_of_nearest(distances)
+    }
+
+    /// Maybe add the behavior to the archive. Until there are enough behaviors to find a full set
+    /// of neighbors, every one gets in. Returns whether it did.
+    pub fn consider(&mut self, behavior: Behavior, rng: &mut impl Rng) -> bool {
+        let joins = self.behaviors.len() < NEAREST || rng.gen_bool(ARCHIVE_RATE);
+
+        if joins {
+            self.behaviors.push(behavior);
+        }
+
+        joins
+    }
+}
+
+fn average_of_nearest(mut distances: Vec<f64>) -> f64 {
+    if distances.is_empty() {
+        return 0.0;
+    }
+
+    distances.sort_by(|a, b| a.total_cmp(b));
+    distances.truncate(NEAREST);
+
+    distances.iter().sum::<f64>() / distances.len() as f64
+}
+
+#[test]
+fn novelty_is_distance_from_the_nearest() {
+    let mut rng = rand::thread_rng();
+    let mut archive = Archive::default();
+
+    for n in 0..5 {
+        archive.consider(Behavior(vec![n as f64, 0.0]), &mut rng);
+    }
+
+    let seen_before = Behavior(vec![2.0, 0.0]);
+    let far_away = Behavior(vec![2.0, 100.0]);
+
+    assert!(
+        archive.novelty(&far_away, [].into_iter()) > archive.novelty(&seen_before, [].into_iter())
+    );
+
+    // A behavior in the archive isn't its own nearest neighbor
+    assert_eq!(archive.novelty(&seen_before, [].into_iter()), 1.2);
+    assert_eq!(archive.novelty_of_member(&seen_before, [].into_iter()), 1.5);
+    assert_eq!(far_away.to_string().parse::<Behavior>(), Ok(far_away));
+}
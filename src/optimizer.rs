@@ -0,0 +1,186 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::evolver::{self, solve};
+use crate::fitness::Fitness;
+use crate::novelty::{Archive, Behavior};
+use crate::{board, genome};
+
+// The optimizer's responsibility is to polish a single board, where the evolver breeds whole
+// populations of them. It flips one cell at a time and runs the board again to see whether that
+// helped, which is a lot of runs for a small change, but it's good at squeezing the last bit of
+// period or lifespan out of a board evolution already got close.
+//
+// It only ever works on one board at a time, so it runs on the calling thread. There's no
+// population or archive to measure novelty against, so that's measured against the behaviors of
+// whatever boards it's given to stand in for them.
+
+/// How the optimizer decides whether to keep a change
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Method {
+    /// Keep any change that doesn't make the board less fit. Fast, but gets stuck on the first
+    /// board no single flip can improve.
+    #[default]
+    HillClimbing,
+
+    /// Also keep changes that make the board less fit, sometimes. The worse the change and the
+    /// further along the run, the less likely, so it wanders early and settles down late.
+    Annealing,
+}
+
+pub struct Optimizer {
+    /// What boards get run with and measured by, same as evolution
+    config: evolver::Config,
+
+    method: Method,
+
+    /// How many single cell changes to try
+    steps: usize,
+
+    /// How much fitness annealing is willing to give up at the start, roughly
+    temperature: f64,
+
+    /// What the temperature gets multiplied by after every step
+    cooling: f64,
+
+    /// What novelty is measured against
+    neighbors: Vec<Behavior>,
+
+    rng: StdRng,
+}
+
+impl Optimizer {
+    pub fn new(config: evolver::Config, method: Method, steps: usize) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed.unwrap_or_else(rand::random));
+
+        Self {
+            config,
+            method,
+            steps,
+            temperature: 100.0,
+            cooling: 0.995,
+            neighbors: vec![],
+            rng,
+        }
+    }
+
+    /// Measure novelty against these behaviors, usually those of the population the board came
+    /// from
+    pub fn with_neighbors(mut self, neighbors: Vec<Behavior>) -> Self {
+        self.neighbors = neighbors;
+        self
+    }
+
+    /// Set annealing's temperature schedule
+    pub fn with_schedule(mut self, temperature: f64, cooling: f64) -> Self {
+        self.temperature = temperature;
+        self.cooling = cooling;
+        self
+    }
+
+    /// Run a board and score its novelty, if the fitness needs it
+    pub fn solve(&self, initial: board::Initial) -> board::Solved {
+        let mut solved = solve(&self.config, initial, None, 0);
+
+        if let Some(behavior) = &solved.behavior {
+            solved.novelty = Some(Archive::default().novelty(behavior, self.neighbors.iter()));
+        }
+
+        solved
+    }
+
+    /// The fittest board found along the way, which is the starting board if nothing beat it
+    pub fn optimize(&mut self, start: board::Initial) -> board::Solved {
+        let mut current = self.solve(start);
+        let mut current_fitness = self.config.fitness.measure(&current);
+
+        let mut best = current.clone();
+        let mut best_fitness = current_fitness;
+
+        for step in 0..self.steps {
+            let mut candidate = current.initial.clone();
+            candidate.cells = genome::flip_cell(candidate.size, &candidate.cells, &mut self.rng);
+
+            let candidate = self.solve(candidate);
+            let fitness = self.config.fitness.measure(&candidate);
+
+            if self.accepts(fitness - current_fitness, step) {
+                current = candidate;
+                current_fitness = fitness;
+            }
+
+            if current_fitness > best_fitness {
+                println!(
+                    "step {} found a board with {} cells, for a fitness of {}",
+                    step,
+                    current.initial.cells.len(),
+                    current_fitness
+                );
+
+                best = current.clone();
+                best_fitness = current_fitness;
+            }
+        }
+
+        best
+    }
+
+    /// Whether to move on to a board whose fitness is this much more than the current one's
+    fn accepts(&mut self, change: f64, step: usize) -> bool {
+        if change >= 0.0 {
+            return true;
+        }
+
+        match self.method {
+            Method::HillClimbing => false,
+            Method::Annealing => {
+                let temperature = self.temperature * self.cooling.powi(step as i32);
+                temperature > 0.0 && self.rng.gen_bool((change / temperature).exp())
+            }
+        }
+    }
+}
+
+#[test]
+fn only_annealing_takes_worse_boards() {
+    let config = evolver::Config {
+        seed: Some(3),
+        ..evolver::Config::default()
+    };
+
+    let mut climber = Optimizer::new(config.clone(), Method::HillClimbing, 100);
+    assert!(climber.accepts(0.0, 0));
+    assert!((0..100).all(|step| !climber.accepts(-1.0, step)));
+
+    // Hot enough, a small step down is almost always taken
+    let mut annealer =
+        Optimizer::new(config.clone(), Method::Annealing, 100).with_schedule(1000.0, 1.0);
+    assert!((0..100).any(|step| annealer.accepts(-1.0, step)));
+
+    // And frozen, never
+    let mut frozen = Optimizer::new(config, Method::Annealing, 100).with_schedule(0.0, 1.0);
+    assert!((0..100).all(|step| !frozen.accepts(-1.0, step)));
+}
+
+#[test]
+fn never_ends_up_worse_than_it_started() {
+    let config = evolver::Config {
+        seed: Some(11),
+        ..evolver::Config::default()
+    };
+    let start = board::Initial {
+        size: 10,
+        cells: vec![(4, 5), (4, 6), (5, 4), (5, 5), (6, 5)],
+        starting_subdivisions: 1,
+        starting_subdiv_utilization: 1,
+        seed_region: genome::central_region(10),
+    };
+
+    for method in [Method::HillClimbing, Method::Annealing] {
+        let mut optimizer = Optimizer::new(config.clone(), method, 30);
+        let before = config.fitness.measure(&optimizer.solve(start.clone()));
+        let after = config.fitness.measure(&optimizer.optimize(start.clone()));
+
+        assert!(after >= before, "{:?} went from {} to {}", method, before, after);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+use crate::board;
+use crate::fitness::{Fitness, Weighted};
+
+// Pareto evolution goes after several objectives at once without deciding ahead of time how much
+// each is worth. Rather than adding up a weighted fitness, the terms of the fitness spec are each
+// kept as their own objective, maximized if their weight is positive and minimized if it's
+// negative. A board dominates another if it's at least as good at everything and better at
+// something.
+//
+// Boards are ordered the way NSGA-II does it: sorted into fronts, where the first front is every
+// board nothing dominates, the second is every board only the first front dominates, and so on.
+// Within a front, boards off on their own come before ones crowded in with others like them, so
+// the front keeps spread out across the whole trade-off.
+
+/// A board's score on each term of the fitness, signed so higher is always better
+pub fn scores(fitness: &Weighted, board: &board::Solved) -> Vec<f64> {
+    fitness
+        .terms
+        .iter()
+        .map(|(objective, weight)| objective.measure(board) * weight)
+        .collect()
+}
+
+/// Whether a is at least as good as b at everything, and better at something
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+}
+
+/// Indexes into scores, sorted into fronts, best front first
+pub fn fronts(scores: &[Vec<f64>]) -> Vec<Vec<usize>> {
+    // How many boards dominate each board, and which boards each one dominates
+    let mut dominated_by = vec![0; scores.len()];
+    let mut dominates_these = vec![vec![]; scores.len()];
+
+    for a in 0..scores.len() {
+        for b in 0..scores.len() {
+            if dominates(&scores[a], &scores[b]) {
+                dominates_these[a].push(b);
+                dominated_by[b] += 1;
+            }
+        }
+    }
+
+    let mut fronts = vec![];
+    let mut front: Vec<usize> = (0..scores.len())
+        .filter(|index| dominated_by[*index] == 0)
+        .collect();
+
+    // Peel the fronts off one at a time. Whatever's only dominated by the current front is next.
+    while !front.is_empty() {
+        let mut next = vec![];
+
+        for a in &front {
+            for b in &dominates_these[*a] {
+                dominated_by[*b] -= 1;
+                if dominated_by[*b] == 0 {
+                    next.push(*b);
+                }
+            }
+        }
+
+        front.sort();
+        fronts.push(front);
+        front = next;
+    }
+
+    fronts
+}
+
+/// How much room each board in the front has around it, as the sum over every objective of the
+/// gap between its neighbors on either side. The boards at either end of each objective get
+/// infinite room, so they're always kept.
+fn crowding(scores: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+    for (objective, _) in scores[front[0]].iter().enumerate() {
+        let mut sorted: Vec<usize> = (0..front.len()).collect();
+        sorted
+            .sort_by(|a, b| scores[front[*a]][objective].total_cmp(&scores[front[*b]][objective]));
+
+        let score = |n: usize| scores[front[sorted[n]]][objective];
+        let range = score(sorted.len() - 1) - score(0);
+
+        distances[sorted[0]] = f64::INFINITY;
+        distances[sorted[sorted.len() - 1]] = f64::INFINITY;
+
+        if range > 0.0 {
+            for n in 1..sorted.len() - 1 {
+                distances[sorted[n]] += (score(n + 1) - score(n - 1)) / range;
+            }
+        }
+    }
+
+    distances
+}
+
+/// Indexes into scores, best first, by front and then by how much room they have within it
+pub fn order(scores: &[Vec<f64>]) -> Vec<usize> {
+    fronts(scores)
+        .into_iter()
+        .flat_map(|front| {
+            let distances = crowding(scores, &front);
+            let mut front: Vec<(usize, f64)> = front.into_iter().zip(distances).collect();
+            front.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            front.into_iter().map(|(index, _)| index)
+        })
+        .collect()
+}
+
+/// Stand in fitnesses for the selection strategies, higher the further up the order a board is
+pub fn fitnesses(scores: &[Vec<f64>]) -> Vec<f64> {
+    let mut fitnesses = vec![0.0; scores.len()];
+    for (position, index) in order(scores).into_iter().enumerate() {
+        fitnesses[index] = -(position as f64);
+    }
+    fitnesses
+}
+
+/// A board on a run's Pareto front, with what it takes to show off the trade-off after it's been
+/// replaced
+#[derive(Debug, Clone, PartialEq)]
+pub struct Point {
+    pub board_id: i64,
+    pub initial_cells: usize,
+    pub iterations: usize,
+
+    /// What the board measured on each term of the fitness, unsigned
+    pub measures: Vec<f64>,
+}
+
+impl Point {
+    pub fn new(fitness: &Weighted, board: &board::Saved) -> Point {
+        Point {
+            board_id: board.id,
+            initial_cells: board.solved.initial.cells.len(),
+            iterations: board.solved.iterations,
+            measures: fitness
+                .terms
+                .iter()
+                .map(|(objective, _)| objective.measure(&board.solved))
+                .collect(),
+        }
+    }
+}
+
+/// The boards nothing else in a run's population dominates, as of the last time it changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Front {
+    pub run_id: i64,
+    pub fitness: Weighted,
+    pub points: Vec<Point>,
+}
+
+/// The boards from fewest initial cells to most, with how long each lasted, so the trade-off
+/// between the two reads top to bottom
+impl fmt::Display for Front {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut points: Vec<&Point> = self.points.iter().collect();
+        points.sort_by_key(|point| (point.initial_cells, point.iterations));
+
+        for point in points {
+            let measures: Vec<String> = self
+                .fitness
+                .terms
+                .iter()
+                .zip(&point.measures)
+                .map(|((objective, _), measure)| format!("{} {}", objective.name(), measure))
+                .collect();
+
+            writeln!(
+                f,
+                "id: {} || {} initial cells || {} iterations || {}",
+                point.board_id,
+                point.initial_cells,
+                point.iterations,
+                measures.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn sorts_into_fronts() {
+    // Maximizing the first and minimizing the second, like longevity and initial cells
+    let scores = vec![
+        vec![100.0, -10.0],
+        vec![50.0, -5.0],
+        vec![40.0, -6.0],
+        vec![10.0, -1.0],
+        vec![100.0, -20.0],
+    ];
+
+    assert!(dominates(&scores[1], &scores[2]));
+    assert!(!dominates(&scores[0], &scores[1]));
+    assert!(!dominates(&scores[0], &scores[0]));
+
+    assert_eq!(fronts(&scores), vec![vec![0, 1, 3], vec![2, 4]]);
+
+    // Both ends of the first front come before its middle
+    let order = order(&scores);
+    assert_eq!(order[2], 1);
+    assert_eq!(order[3..].to_vec(), vec![2, 4]);
+}
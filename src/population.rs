@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use rand::Rng;
+use rusqlite::Error;
+
+use crate::fitness::{Fitness, Weighted};
+use crate::map_elites::Descriptors;
+use crate::novelty::{Archive, Behavior};
+use crate::{board, pareto, Db};
+
+// The population's responsibility is to hold the boards every evolver thread is working with, in
+// memory, so the hot loop never has to go to the database to read them. It's shared between the
+// threads, behind a lock.
+//
+// Writes are kept in memory too, and queued up for the database to catch up on whenever flush is
+// called (see main's evolve). Board ids are handed out here rather than by SQLite, so a board has
+// its id the moment it joins the population. Those ids are only a guess at what SQLite will pick
+// though, since anything else writing to the database (another evolve, optimize, search) takes ids
+// too. So boards are saved under whatever id SQLite gives them, and later changes that mention a
+// board by its in memory id are pointed at that. Anything not flushed yet when the program's
+// killed is lost.
+//
+// Under generational replacement, children wait here until there are enough of them to take over
+// the population in one go. Every thread adds to the same generation, so it fills up as fast as
+// all of them together breed. They're only saved once they join the population.
+//
+// In novelty mode the population also keeps the archive of behaviors seen before, which only ever
+// lives in memory. A fresh run starts out comparing boards against just the population. Every time
+// the archive grows, the boards in the population are scored again, and their new scores saved.
+//
+// In map-elites mode the population is the grid of elites, and every board saved from it gets its
+// spot on the grid saved along with it.
+//
+// In pareto mode the run's Pareto front gets saved whenever it changes, see pareto. Only the
+// latest front is worth writing, so any earlier ones still waiting when flush comes around are
+// skipped.
+
+/// A change to the population the database hasn't caught up on yet
+enum Change {
+    Save(Box<board::Saved>),
+    Delete(i64),
+    Front(pareto::Front),
+    Novelty(i64, f64),
+}
+
+pub struct Population {
+    members: RwLock<Vec<board::Saved>>,
+    pending: Mutex<Vec<Change>>,
+
+    /// Shared between islands, so no two of them hand out the same id
+    next_id: Arc<AtomicI64>,
+
+    archive: Mutex<Archive>,
+
+    /// Children waiting to take over the population all at once, under generational replacement
+    next_generation: Mutex<Vec<board::Solved>>,
+
+    /// What the boards are laid out by, if the population's a grid of elites
+    descriptors: Option<Descriptors>,
+
+    /// The id each flushed board was actually saved under, by the id it has in memory
+    saved_as: Mutex<HashMap<i64, i64>>,
+}
+
+/// The population, locked for writing. Anything added or removed here gets queued for the
+/// database.
+pub struct Members<'a> {
+    boards: &'a mut Vec<board::Saved>,
+    pending: &'a mut Vec<Change>,
+    next_id: &'a AtomicI64,
+    next_generation: &'a mut Vec<board::Solved>,
+}
+
+impl Population {
+    /// Load the fittest population_size boards evolved under the given fitness. Every earlier run
+    /// under the same spec left its boards behind, so there are usually more than that.
+    pub fn load(db: &Db, fitness: &Weighted, population_size: usize) -> Result<Population, Error> {
+        let boards = db.load_boards_evolved_under(&fitness.to_string(), None)?;
+        Population::new(db, fittest(boards, fitness, population_size), None)
+    }
+
+    /// Load a population for each island, evolved under the island's fitness, in order
+    pub fn load_islands(
+        db: &Db,
+        fitnesses: &[Weighted],
+        population_size: usize,
+    ) -> Result<Vec<Population>, Error> {
+        let next_id = Arc::new(AtomicI64::new(db.get_max_board_id()? + 1));
+
+        fitnesses
+            .iter()
+            .enumerate()
+            .map(|(island, fitness)| {
+                let boards =
+                    db.load_boards_evolved_under(&fitness.to_string(), Some(island as u32))?;
+                let boards = fittest(boards, fitness, population_size);
+                let mut population = Population::new(db, boards, None)?;
+                population.next_id = Arc::clone(&next_id);
+                Ok(population)
+            })
+            .collect()
+    }
+
+    /// Load the grid of elites evolved under the given fitness spec and laid out by the given
+    /// descriptors
+    pub fn load_elites(
+        db: &Db,
+        fitness: &str,
+        descriptors: Descriptors,
+    ) -> Result<Population, Error> {
+        let boards = db.load_elites(fitness, &descriptors)?;
+        Population::new(db, boards, Some(descriptors))
+    }
+
+    fn new(
+        db: &Db,
+        boards: Vec<board::Saved>,
+        descriptors: Option<Descriptors>,
+    ) -> Result<Population, Error> {
+        Ok(Population {
+            members: RwLock::new(boards),
+            pending: Mutex::new(vec![]),
+            next_id: Arc::new(AtomicI64::new(db.get_max_board_id()? + 1)),
+            archive: Mutex::new(Archive::default()),
+            next_generation: Mutex::new(vec![]),
+            descriptors,
+            saved_as: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Look at the boards
+    pub fn read<T>(&self, f: impl FnOnce(&[board::Saved]) -> T) -> T {
+        f(&self.members.read().unwrap())
+    }
+
+    /// Change the boards. Nobody else can read or change them until this is done, so everything
+    /// done in here happens all at once.
+    pub fn update<T>(&self, f: impl FnOnce(&mut Members) -> T) -> T {
+        let mut boards = self.members.write().unwrap();
+        let mut pending = self.pending.lock().unwrap();
+        let mut next_generation = self.next_generation.lock().unwrap();
+
+        f(&mut Members {
+            boards: &mut boards,
+            pending: &mut pending,
+            next_id: &self.next_id,
+            next_generation: &mut next_generation,
+        })
+    }
+
+    /// How far a behavior is from what the population and the archive have already done, see
+    /// novelty. The behavior then gets its chance at joining the archive, and if it does, the
+    /// population is scored again.
+    pub fn novelty(&self, behavior: &Behavior, rng: &mut impl Rng) -> f64 {
+        let mut boards = self.members.write().unwrap();
+        let mut archive = self.archive.lock().unwrap();
+
+        let novelty = archive.novelty(
+            behavior,
+            boards
+                .iter()
+                .filter_map(|board| board.solved.behavior.as_ref()),
+        );
+
+        if archive.consider(behavior.clone(), rng) {
+            let rescored: Vec<(usize, f64)> = boards
+                .iter()
+                .enumerate()
+                .filter_map(|(index, board)| {
+                    let behavior = board.solved.behavior.as_ref()?;
+                    let others = boards
+                        .iter()
+                        .enumerate()
+                        .filter(|(other, _)| *other != index)
+                        .filter_map(|(_, other)| other.solved.behavior.as_ref());
+
+                    Some((index, archive.novelty_of_member(behavior, others)))
+                })
+                .collect();
+
+            let mut pending = self.pending.lock().unwrap();
+            for (index, novelty) in rescored {
+                boards[index].solved.novelty = Some(novelty);
+                pending.push(Change::Novelty(boards[index].id, novelty));
+            }
+        }
+
+        novelty
+    }
+
+    /// Bring the database up to date with every change since the last flush. If one of them
+    /// fails, it and everything after it are kept for the next flush to try again.
+    pub fn flush(&self, db: &mut Db) -> Result<(), Error> {
+        // Take the changes and let go of the lock right away, so the evolvers aren't held up on
+        // the database. An evolver that panicked while holding the lock doesn't make its changes
+        // any less worth saving.
+        let mut changes: Vec<Change> = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain(..)
+            .collect();
+        let latest_front = changes
+            .iter()
+            .rposition(|change| matches!(change, Change::Front(_)));
+
+        for index in 0..changes.len() {
+            if let Err(error) = self.apply(db, &changes[index], Some(index) == latest_front) {
+                let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+                let newer = std::mem::take(&mut *pending);
+                pending.extend(changes.drain(index..));
+                pending.extend(newer);
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a single change to the database, under the ids its boards were really saved as
+    fn apply(&self, db: &mut Db, change: &Change, latest_front: bool) -> Result<(), Error> {
+        let mut saved_as = self.saved_as.lock().unwrap();
+
+        match change {
+            Change::Save(board) => {
+                let id = match &self.descriptors {
+                    Some(descriptors) => db.save_elite_board(
+                        &board.solved,
+                        descriptors,
+                        descriptors.cell(&board.solved),
+                    )?,
+                    None => db.save_board(&board.solved)?,
+                };
+                saved_as.insert(board.id, id);
+            }
+            Change::Delete(id) => {
+                db.delete_board(saved_as.get(id).unwrap_or(id))?;
+                saved_as.remove(id);
+            }
+            Change::Front(front) if latest_front => {
+                let mut front = front.clone();
+                for point in &mut front.points {
+                    point.board_id = *saved_as.get(&point.board_id).unwrap_or(&point.board_id);
+                }
+                db.save_front(&front)?;
+            }
+            Change::Front(_) => {}
+            Change::Novelty(id, novelty) => {
+                db.update_novelty(*saved_as.get(id).unwrap_or(id), *novelty)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Members<'a> {
+    pub fn boards(&self) -> &[board::Saved] {
+        self.boards
+    }
+
+    /// Add a board, and get back the id it goes by in memory. It may be saved under another one,
+    /// but later changes that use this id follow it there.
+    pub fn add(&mut self, mut solved: board::Solved) -> i64 {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+
+        // The per generation stats can be huge, and are only needed by the database
+        let stats = solved.stats.take();
+        self.boards.push(board::Saved {
+            id,
+            solved: solved.clone(),
+        });
+        solved.stats = stats;
+
+        self.pending
+            .push(Change::Save(Box::new(board::Saved { id, solved })));
+
+        id
+    }
+
+    pub fn remove(&mut self, id: i64) {
+        self.boards.retain(|board| board.id != id);
+        self.pending.push(Change::Delete(id));
+    }
+
+    /// Hold on to a child for the next generation, and get back how many are waiting now
+    pub fn hold_for_next_generation(&mut self, solved: board::Solved) -> usize {
+        self.next_generation.push(solved);
+        self.next_generation.len()
+    }
+
+    /// Take every child held for the next generation, leaving none waiting
+    pub fn take_next_generation(&mut self) -> Vec<board::Solved> {
+        self.next_generation.drain(..).collect()
+    }
+
+    /// Record what the run's Pareto front is now
+    pub fn set_front(&mut self, front: pareto::Front) {
+        self.pending.push(Change::Front(front));
+    }
+}
+
+/// The population_size fittest of the boards, fittest first
+fn fittest(
+    mut boards: Vec<board::Saved>,
+    fitness: &Weighted,
+    population_size: usize,
+) -> Vec<board::Saved> {
+    boards.sort_by(|a, b| {
+        fitness
+            .measure(&b.solved)
+            .total_cmp(&fitness.measure(&a.solved))
+    });
+    boards.truncate(population_size);
+    boards
+}
+
+#[test]
+fn changes_reach_the_database_on_flush() {
+    let manager = r2d2_sqlite::SqliteConnectionManager::memory();
+    let pool = r2d2::Pool::new(manager).unwrap();
+    Db::initialize(pool.get().unwrap());
+    let mut db = Db::new(pool.get().unwrap());
+
+    let population = Population::load(&db, &Weighted::default(), 10).unwrap();
+    let solved = board::Solved {
+        initial: board::Initial {
+            size: 10,
+            cells: vec![(1, 1)],
+            starting_subdivisions: 1,
+            starting_subdiv_utilization: 1,
+            seed_region: crate::genome::central_region(10),
+        },
+        iterations: 1,
+        period: None,
+        cycle_start: None,
+        census: crate::Census::default(),
+        stats: None,
+        emissions: vec![],
+        growth: None,
+        final_population: 0,
+        peak_population: 1,
+        final_bounding_box: None,
+        fitness: String::from(crate::fitness::DEFAULT_SPEC),
+        run_id: None,
+        reproduces_as: None,
+        origin_similarity: None,
+        behavior: None,
+        novelty: None,
+        island: None,
+    };
+
+    let (kept, removed) = population.update(|members| {
+        let kept = members.add(solved.clone());
+        let removed = members.add(solved.clone());
+        members.remove(removed);
+        (kept, removed)
+    });
+
+    assert_ne!(kept, removed);
+    assert_eq!(population.read(|boards| boards.len()), 1);
+    assert_eq!(db.get_board_count().unwrap(), 0);
+
+    population.flush(&mut db).unwrap();
+
+    let saved = db.load_boards().unwrap();
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].id, kept);
+
+    // A new population picks up where the old one's ids left off
+    let population = Population::load(&db, &Weighted::default(), 10).unwrap();
+    let clashing = population.update(|members| members.add(solved.clone()));
+    assert!(clashing > removed);
+
+    // Anyone else saving a board in the meantime takes the id the population had in mind, so its
+    // board goes in under the next one, and later changes to it follow it there
+    let elsewhere = db.save_board(&solved).unwrap();
+    assert_eq!(clashing, elsewhere);
+    population.flush(&mut db).unwrap();
+    population.update(|members| members.remove(clashing));
+    population.flush(&mut db).unwrap();
+
+    let ids: Vec<i64> = db.load_boards().unwrap().iter().map(|board| board.id).collect();
+    assert_eq!(ids, vec![kept, elsewhere]);
+
+    // Earlier runs only get to fill the population up, not go over it
+    let population = Population::load(&db, &Weighted::default(), 1).unwrap();
+    assert_eq!(population.read(|boards| boards.len()), 1);
+
+    // Every update sees the same generation waiting, whichever thread it's from
+    population.update(|members| members.hold_for_next_generation(solved.clone()));
+    assert_eq!(population.update(|members| members.hold_for_next_generation(solved.clone())), 2);
+    assert_eq!(population.update(|members| members.take_next_generation()).len(), 2);
+    assert!(population.update(|members| members.take_next_generation()).is_empty());
+
+    // Islands share their ids, so boards from any two of them never clash
+    let fitness = Weighted::default();
+    let islands = Population::load_islands(&db, &[fitness.clone(), fitness], 10).unwrap();
+    let first = islands[0].update(|members| members.add(solved.clone()));
+    assert_ne!(first, islands[1].update(|members| members.add(solved)));
+}
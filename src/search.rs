@@ -0,0 +1,238 @@
+use std::str::FromStr;
+
+use crate::board;
+use crate::cells::BoundingBox;
+use crate::evolver::{self, solve};
+use crate::fitness::{Fitness, Objective};
+use crate::snapshot::Symmetry;
+
+// Search's responsibility is to try every pattern that fits in a small box, rather than breeding
+// its way towards good ones like the evolver. It's only feasible for small boxes, there are 2^(n*m)
+// ways to fill an n by m box, but what it finds there is the whole truth, which makes it something
+// to check the evolver against.
+//
+// Patterns that are rotations, reflections or translations of each other all play out the same,
+// so only one of each is run.
+
+/// Why a pattern was kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// One of the few that ran the longest before dying, looping or being found to grow forever
+    LongestLived,
+
+    /// It settled into a loop of one of the periods being looked for, without any spaceships
+    Oscillator,
+
+    /// It made a spaceship or turned into one
+    Spaceship,
+}
+
+impl Reason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Reason::LongestLived => "longest-lived",
+            Reason::Oscillator => "oscillator",
+            Reason::Spaceship => "spaceship",
+        }
+    }
+}
+
+impl FromStr for Reason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "longest-lived" => Ok(Reason::LongestLived),
+            "oscillator" => Ok(Reason::Oscillator),
+            "spaceship" => Ok(Reason::Spaceship),
+            _ => Err(format!("unknown search reason {}", s)),
+        }
+    }
+}
+
+/// Which patterns to keep
+#[derive(Debug, Clone, PartialEq)]
+pub struct Criteria {
+    /// How many of the longest lived patterns
+    pub longest: usize,
+
+    /// Every oscillator of any of these periods. Period 1 is still lifes.
+    pub periods: Vec<usize>,
+
+    /// Every pattern that made or turned into a spaceship
+    pub spaceships: bool,
+}
+
+/// A pattern that met the criteria, and every one of them it met
+#[derive(Debug, Clone)]
+pub struct Find {
+    pub reasons: Vec<Reason>,
+    pub board: board::Solved,
+}
+
+/// The most cells a box can have for a search to get through it. Every pattern has to be looked
+/// at before the ones that are only rotations or reflections can be skipped, and 2^25 is about as
+/// many as that's worth.
+pub const MAX_CELLS: u32 = 25;
+
+/// Every pattern that fits in a box this many cells across and down, one for each shape
+pub fn patterns(width: u32, height: u32) -> impl Iterator<Item = Vec<(u32, u32)>> {
+    let spots = width * height;
+    assert!(
+        spots <= MAX_CELLS,
+        "a {}x{} box has 2^{} patterns to try",
+        width,
+        height,
+        spots
+    );
+
+    (1u64..1 << spots)
+        .map(move |bits| {
+            (0..spots)
+                .filter(|spot| bits >> spot & 1 == 1)
+                .map(|spot| (spot / width, spot % width))
+                .collect::<Vec<(u32, u32)>>()
+        })
+        .filter(move |cells| canonical(cells, width, height) == *cells)
+}
+
+/// The one pattern that stands in for every rotation, reflection and translation of this one
+/// that fits in the box: whichever of them comes first in sorted order once it's been moved up
+/// against the top left corner. Cells come and go sorted.
+fn canonical(cells: &[(u32, u32)], width: u32, height: u32) -> Vec<(u32, u32)> {
+    Symmetry::ALL
+        .iter()
+        .filter_map(|symmetry| {
+            let moved: Vec<(i64, i64)> = cells
+                .iter()
+                .map(|(i, j)| symmetry.apply((*i as i64, *j as i64)))
+                .collect();
+            let top = moved.iter().map(|(i, _)| *i).min().unwrap();
+            let left = moved.iter().map(|(_, j)| *j).min().unwrap();
+
+            let mut moved: Vec<(u32, u32)> = moved
+                .into_iter()
+                .map(|(i, j)| ((i - top) as u32, (j - left) as u32))
+                .collect();
+            moved.sort();
+
+            moved
+                .iter()
+                .all(|(i, j)| *i < height && *j < width)
+                .then_some(moved)
+        })
+        .min()
+        .unwrap()
+}
+
+/// Run every pattern in the box in the middle of a board of the given size, and keep the ones
+/// that meet the criteria
+pub fn search(
+    size: u32,
+    width: u32,
+    height: u32,
+    criteria: &Criteria,
+    config: &evolver::Config,
+) -> Vec<Find> {
+    let region = BoundingBox {
+        top: (size + 1 - height) / 2,
+        left: (size + 1 - width) / 2,
+        height,
+        width,
+    };
+
+    let mut finds = vec![];
+    let mut longest: Vec<board::Solved> = vec![];
+
+    for (tried, cells) in patterns(width, height).enumerate() {
+        if tried > 0 && tried % 10000 == 0 {
+            println!("tried {} patterns", tried);
+        }
+
+        let initial = board::Initial {
+            size,
+            cells: cells
+                .iter()
+                .map(|(i, j)| (region.top + i, region.left + j))
+                .collect(),
+            starting_subdivisions: 1,
+            starting_subdiv_utilization: 1,
+            seed_region: region,
+        };
+        let solved = solve(config, initial, None, 0);
+
+        let spaceship = Objective::SpaceshipFound.measure(&solved) > 0.0;
+        let oscillator = solved
+            .period
+            .is_some_and(|period| criteria.periods.contains(&period));
+
+        if criteria.spaceships && spaceship {
+            finds.push(Find {
+                reasons: vec![Reason::Spaceship],
+                board: solved.clone(),
+            });
+        } else if oscillator && !spaceship {
+            finds.push(Find {
+                reasons: vec![Reason::Oscillator],
+                board: solved.clone(),
+            });
+        }
+
+        // Ties go to whichever was tried first
+        let position = longest.partition_point(|other| other.iterations >= solved.iterations);
+        if position < criteria.longest {
+            longest.insert(position, solved);
+            longest.truncate(criteria.longest);
+        }
+    }
+
+    // A long lived pattern that was already kept for something else is only kept the once
+    for board in longest {
+        match finds
+            .iter_mut()
+            .find(|find| find.board.initial.cells == board.initial.cells)
+        {
+            Some(find) => find.reasons.push(Reason::LongestLived),
+            None => finds.push(Find {
+                reasons: vec![Reason::LongestLived],
+                board,
+            }),
+        }
+    }
+
+    finds
+}
+
+#[test]
+fn searches_each_shape_once() {
+    // A cell, a domino, a diagonal pair, an L and a block
+    assert_eq!(patterns(2, 2).count(), 5);
+
+    // A cell, a pair, a pair with a gap and a line of three. Standing up they don't fit.
+    assert_eq!(patterns(3, 1).count(), 4);
+
+    let criteria = Criteria {
+        longest: 3,
+        periods: vec![2],
+        spaceships: true,
+    };
+    let finds = search(32, 3, 3, &criteria, &evolver::Config::default());
+    let found = |reason| {
+        finds
+            .iter()
+            .filter(|find| find.reasons.contains(&reason))
+            .count()
+    };
+
+    // The glider's the only spaceship that fits, but some of its predecessors fit too
+    assert!(found(Reason::Spaceship) > 0);
+    assert!(found(Reason::Oscillator) > 0);
+    assert_eq!(found(Reason::LongestLived), 3);
+
+    // Every pattern's only kept the once, however many reasons there are to keep it
+    for (index, find) in finds.iter().enumerate() {
+        assert!(finds[index + 1..]
+            .iter()
+            .all(|other| other.board.initial.cells != find.board.initial.cells));
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{Rng, RngCore};
+
+// Selection's responsibility is to pick which boards in the population get to be parents. Every
+// strategy favors fitter boards, they differ in how strongly, which is the main knob on how fast a
+// population converges (and how likely it is to get stuck).
+
+/// Picks a parent out of a population, given everyone's fitness. Higher is fitter.
+pub trait Selection {
+    /// The index of the chosen board. Panics if there aren't any.
+    fn select(&self, fitnesses: &[f64], rng: &mut dyn RngCore) -> usize;
+}
+
+/// The built in selection strategies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    /// Pick this many boards at random, and take the fittest of them
+    Tournament(usize),
+
+    /// Pick with probability proportional to fitness. Fitnesses are shifted up so the least fit
+    /// board has none, when any are negative.
+    Roulette,
+
+    /// Pick with probability proportional to rank, so the least fit board has a weight of 1 and
+    /// the fittest has a weight of the population's size
+    Rank,
+
+    /// Pick uniformly from the fittest fraction of the population
+    Truncation(f64),
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Tournament(3)
+    }
+}
+
+impl Selection for Strategy {
+    fn select(&self, fitnesses: &[f64], rng: &mut dyn RngCore) -> usize {
+        assert!(
+            !fitnesses.is_empty(),
+            "can't select from an empty population"
+        );
+
+        match self {
+            Strategy::Tournament(size) => (0..(*size).max(1))
+                .map(|_| rng.gen_range(0..fitnesses.len()))
+                .max_by(|a, b| fitnesses[*a].total_cmp(&fitnesses[*b]))
+                .unwrap(),
+
+            Strategy::Roulette => {
+                let least = fitnesses.iter().copied().fold(0.0, f64::min);
+                let weights: Vec<f64> = fitnesses.iter().map(|f| f - least).collect();
+                spin(&weights, rng)
+            }
+
+            Strategy::Rank => {
+                let mut weights = vec![0.0; fitnesses.len()];
+                for (rank, index) in by_fitness(fitnesses).into_iter().enumerate() {
+                    weights[index] = (rank + 1) as f64;
+                }
+                spin(&weights, rng)
+            }
+
+            Strategy::Truncation(fraction) => {
+                let ranked = by_fitness(fitnesses);
+                let kept =
+                    ((ranked.len() as f64 * fraction).ceil() as usize).clamp(1, ranked.len());
+                ranked[ranked.len() - kept + rng.gen_range(0..kept)]
+            }
+        }
+    }
+}
+
+/// Indexes of the fitnesses, least fit first
+fn by_fitness(fitnesses: &[f64]) -> Vec<usize> {
+    let mut indexes: Vec<usize> = (0..fitnesses.len()).collect();
+    indexes.sort_by(|a, b| fitnesses[*a].total_cmp(&fitnesses[*b]));
+    indexes
+}
+
+/// Pick an index with probability proportional to its weight, or uniformly if they're all 0
+fn spin(weights: &[f64], rng: &mut dyn RngCore) -> usize {
+    let total: f64 = weights.iter().sum();
+
+    if total <= 0.0 {
+        return rng.gen_range(0..weights.len());
+    }
+
+    let mut remaining = rng.gen_range(0.0..total);
+    for (index, weight) in weights.iter().enumerate() {
+        if remaining < *weight {
+            return index;
+        }
+        remaining -= weight;
+    }
+
+    // Rounding can leave a sliver past the end
+    weights.len() - 1
+}
+
+/// Parses specs like "tournament=3", "roulette", "rank" or "truncation=0.5"
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(2, '=');
+        let name = parts.next().unwrap();
+        let parameter = parts.next();
+
+        match (name, parameter) {
+            ("tournament", Some(size)) => match size.parse() {
+                Ok(size) if size > 0 => Ok(Strategy::Tournament(size)),
+                _ => Err(format!("bad tournament size {}", size)),
+            },
+            ("tournament", None) => Ok(Strategy::default()),
+            ("roulette", None) => Ok(Strategy::Roulette),
+            ("rank", None) => Ok(Strategy::Rank),
+            ("truncation", Some(fraction)) => match fraction.parse() {
+                Ok(fraction) if fraction > 0.0 && fraction <= 1.0 => {
+                    Ok(Strategy::Truncation(fraction))
+                }
+                _ => Err(format!("bad truncation fraction {}", fraction)),
+            },
+            ("truncation", None) => Ok(Strategy::Truncation(0.5)),
+            _ => Err(format!(
+                "unknown selection {}, expected one of tournament=K, roulette, rank, truncation=F",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Strategy::Tournament(size) => write!(f, "tournament={}", size),
+            Strategy::Roulette => write!(f, "roulette"),
+            Strategy::Rank => write!(f, "rank"),
+            Strategy::Truncation(fraction) => write!(f, "truncation={}", fraction),
+        }
+    }
+}
+
+#[test]
+fn selection_favors_the_fit() {
+    let mut rng = rand::thread_rng();
+    let fitnesses = [1.0, 50.0, -3.0, 10.0];
+
+    for strategy in ["tournament=3", "roulette", "rank", "truncation=0.25"] {
+        let strategy: Strategy = strategy.parse().unwrap();
+        let mut counts = [0; 4];
+
+        for _ in 0..2000 {
+            counts[strategy.select(&fitnesses, &mut rng)] += 1;
+        }
+
+        assert_eq!(
+            counts.iter().max(),
+            Some(&counts[1]),
+            "{} didn't favor the fittest",
+            strategy
+        );
+    }
+
+    // Only the fittest quarter ever gets picked
+    let strategy = Strategy::Truncation(0.25);
+    assert!((0..100).all(|_| strategy.select(&fitnesses, &mut rng) == 1));
+}
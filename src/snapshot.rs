@@ -1,70 +1,335 @@
 // This needs to:
-// * Keep a list of every state in the history of an evolution
+// * Keep track of every state in the history of an evolution
 // * Be able to compare those states and return equal for two equivalent states
 // * However there's no guarantee from our Cells grid the order in which living cells will be
 //   returned, so we need to be order agnostic
-// * Be fast
+// * Treat two states as equivalent if one is just the other moved somewhere else on the board,
+//   so spaceships like the glider are recognized as soon as they complete a single period
+// * Be fast, and not grow too much in memory as boards run for thousands of iterations
 //
-// Where the <String> within the grids_vec and grids_sec fields comes from the serialization of a
-// BTreeSet. Yes, each cell in the grid can be represented by a number: SIZE*i + j. Thus we can
-// have ordering, and a BTreeSet. That set can be serialized deterministcally into a string, which
-// can be stored in a hashset. Along side this hash set can be a vector with the strings, for
-// ordering. So at each iteration of the grid, we can check in O(1) if this has been a state that
-// we've seen yet, and then if so we can see how far back it happened, and that's our fitness.
+// Each cell in the grid can be represented by a number: WIDTH*i + j. Thus we can have ordering,
+// and a BTreeSet. That ordered list of numbers is run through a 128 bit hash, and only the hash is
+// kept, in a map from hash to the generation it was first seen in. So at each iteration of the
+// grid, we can check in O(1) if this has been a state that we've seen yet, and then if so we know
+// right away how far back it happened, and that's our period.
+//
+// Two different states could in theory share a hash. So when a hash comes up a second time, we
+// hold on to that one state and only call it a repeat once the board has gone through the
+// supposed period again and landed on exactly the same cells.
+//
+// Before hashing, every cell is shifted so the state's bounding box starts at (0, 0). The shift
+// that was taken off is kept alongside the generation, which is how we know how far a repeating
+// pattern has traveled.
+//
+// For really long runs, even a hash per generation adds up. So there's also a strategy that uses
+// Brent's cycle detection, which only ever holds on to a single earlier state (the "tortoise") and
+// moves it forward at powers of two. It finds the period just as well, but since it doesn't
+// remember where the loop started, finding that takes a second run of the board, see
+// find_cycle_start.
+//
+// Optionally, the snapshot can also ignore rotations and reflections. In that mode each state is
+// put through all 8 symmetries of the square, and whichever one comes out smallest is the one
+// we hash. Which symmetry that was is kept too, so we can report, once we find a repeat, how the
+// repeated state relates to the earlier one.
+//
+// When searching for boards that come back to their own starting state, it helps to know how close
+// the ones that don't came. So the snapshot can also hold on to the initial state's cell numbers,
+// and score every later state by how much it overlaps with them.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 
-use std::collections::{BTreeSet, HashSet};
+use crate::emissions::EmissionDetector;
+use crate::{Cells, Game};
 
 pub struct Snapshot {
-    grids_vec: Vec<String>,
-    grids_set: HashSet<String>,
-    current_iteration_cells: BTreeSet<u32>,
+    seen: HashMap<u128, Seen>,
+    candidate: Option<Candidate>,
+    tortoise: Option<Tortoise>,
+    repeat: Option<Repeat>,
+    generation: usize,
+    current_iteration_cells: BTreeSet<(u32, u32)>,
     size: u32,
-    has_repeat: bool,
+    symmetric: bool,
+    strategy: Strategy,
+    track_origin: bool,
+    origin: Option<Vec<u32>>,
+    origin_similarity: Option<f64>,
+}
+
+/// How the snapshot goes about finding loops
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Strategy {
+    /// Remember a hash of every generation. Finds the loop the moment it closes.
+    #[default]
+    Full,
+
+    /// Brent's algorithm. Only remembers a single state, no matter how long the board runs, but
+    /// can take up to a few periods longer to notice the loop.
+    Brent,
+}
+
+/// What we remember about a state the first time we see its hash
+#[derive(Debug, Clone, Copy)]
+struct Seen {
+    generation: usize,
+    offset: (u32, u32),
+    symmetry: Symmetry,
+}
+
+/// A repeat that's been confirmed
+#[derive(Debug, Clone, Copy)]
+struct Repeat {
+    earlier: Seen,
+    later: Seen,
+}
+
+/// A hash we've seen twice, but haven't yet confirmed isn't a collision
+struct Candidate {
+    repeat: Repeat,
+    cells: Vec<u32>,
+    confirm_at: usize,
+}
+
+/// The one state Brent's algorithm holds on to, and how far the board's come since
+struct Tortoise {
+    seen: Seen,
+    hash: u128,
+    cells: Vec<u32>,
+    power: usize,
+    lambda: usize,
+}
+
+/// A single generation, boiled down so that equivalent states come out the same
+struct Canonical {
+    cells: Vec<u32>,
+    offset: (u32, u32),
+    symmetry: Symmetry,
+}
+
+/// The 8 ways a square can be rotated or flipped onto itself. Rotations are clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipRows,
+    FlipColumns,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipRows,
+        Symmetry::FlipColumns,
+        Symmetry::Transpose,
+        Symmetry::AntiTranspose,
+    ];
+
+    /// Move a coordinate around the origin. The result usually needs to be shifted back onto the
+    /// board afterwards.
+    pub fn apply(&self, (i, j): (i64, i64)) -> (i64, i64) {
+        match self {
+            Symmetry::Identity => (i, j),
+            Symmetry::Rotate90 => (j, -i),
+            Symmetry::Rotate180 => (-i, -j),
+            Symmetry::Rotate270 => (-j, i),
+            Symmetry::FlipRows => (-i, j),
+            Symmetry::FlipColumns => (i, -j),
+            Symmetry::Transpose => (j, i),
+            Symmetry::AntiTranspose => (-j, -i),
+        }
+    }
+
+    /// The symmetry that does `other` first, then this one. Two points that aren't on a common
+    /// line through the origin are enough to tell all 8 apart.
+    pub fn then_after(&self, other: Symmetry) -> Symmetry {
+        let probe = (1, 2);
+
+        *Symmetry::ALL
+            .iter()
+            .find(|candidate| candidate.apply(probe) == self.apply(other.apply(probe)))
+            .unwrap()
+    }
+
+    /// The symmetry that undoes this one
+    pub fn inverse(&self) -> Symmetry {
+        *Symmetry::ALL
+            .iter()
+            .find(|candidate| candidate.then_after(*self) == Symmetry::Identity)
+            .unwrap()
+    }
+}
+
+/// How far a repeating pattern moves each time it goes through its period. Stationary patterns
+/// (still lifes and oscillators) have a displacement of (0, 0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Velocity {
+    /// Rows moved per period, positive is down the board
+    pub di: i64,
+
+    /// Columns moved per period, positive is to the right
+    pub dj: i64,
+
+    /// How many generations it takes to make that move
+    pub period: usize,
+}
+
+impl Velocity {
+    pub fn is_stationary(&self) -> bool {
+        self.di == 0 && self.dj == 0
+    }
+}
+
+/// Formats velocities in the usual life notation, like "c/4 diagonal" for the glider or
+/// "c/2 orthogonal" for the lightweight spaceship
+impl fmt::Display for Velocity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_stationary() {
+            return write!(f, "stationary");
+        }
+
+        let distance = self.di.unsigned_abs().max(self.dj.unsigned_abs());
+        let divisor = gcd(distance, self.period as u64);
+        let (distance, period) = (distance / divisor, self.period as u64 / divisor);
+
+        let speed = match (distance, period) {
+            (1, 1) => String::from("c"),
+            (1, _) => format!("c/{}", period),
+            (_, 1) => format!("{}c", distance),
+            _ => format!("{}c/{}", distance, period),
+        };
+
+        let direction = if self.di == 0 || self.dj == 0 {
+            "orthogonal"
+        } else if self.di.abs() == self.dj.abs() {
+            "diagonal"
+        } else {
+            "oblique"
+        };
+
+        write!(f, "{} {}", speed, direction)
+    }
 }
 
 impl Snapshot {
     pub fn new(size: u32) -> Snapshot {
         Snapshot {
-            grids_vec: Vec::new(),
-            grids_set: HashSet::new(),
+            seen: HashMap::new(),
+            candidate: None,
+            tortoise: None,
+            repeat: None,
+            generation: 0,
             current_iteration_cells: BTreeSet::new(),
             size,
-            has_repeat: false,
+            symmetric: false,
+            strategy: Strategy::Full,
+            track_origin: false,
+            origin: None,
+            origin_similarity: None,
         }
     }
 
+    /// Pick how loops get found, see Strategy
+    pub fn with_strategy(mut self, strategy: Strategy) -> Snapshot {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Also count a state as a repeat when it comes back rotated or flipped. Oscillators whose
+    /// phases are mirror images of each other then get caught at a fraction of their full period.
+    pub fn with_symmetry(mut self) -> Snapshot {
+        self.symmetric = true;
+        self
+    }
+
+    /// Keep the initial state around, and score every state after it by how similar it is, see
+    /// origin_similarity
+    pub fn with_origin_similarity(mut self) -> Snapshot {
+        self.track_origin = true;
+        self
+    }
+
     /// Add a single cell to the uncommitted memory. The reason we do one cell at a time
     /// instead of all of them at once is so that we only have to go through the whole list
     /// of cells a single time per board iteration. One loop over all of them is enough :)
     pub fn add_cell(&mut self, i: u32, j: u32) {
-        let cell_number = self.size * i + j;
-        self.current_iteration_cells.insert(cell_number);
+        self.current_iteration_cells.insert((i, j));
     }
 
-    /// Is the current build up snapshot the same as the previous one, the one that most recently
-    /// got cycled?
+    /// Has the board gotten back to a state it's been in before?
     pub fn has_repeat(&self) -> bool {
-        self.has_repeat
+        self.repeat.is_some()
     }
 
     // If has_repeat is true, this will return how long the repeat period is
     pub fn period(&self) -> Option<usize> {
-        if !self.has_repeat {
+        self.repeat
+            .map(|repeat| repeat.later.generation - repeat.earlier.generation)
+    }
+
+    /// If has_repeat is true, the generation the loop began on, counting the initial state as
+    /// generation 0. Brent's algorithm doesn't remember this, so it's None under that strategy, and
+    /// find_cycle_start can be used instead.
+    pub fn cycle_start(&self) -> Option<usize> {
+        match self.strategy {
+            // The first hash to come up twice is the first state of the loop
+            Strategy::Full => self.repeat.map(|repeat| repeat.earlier.generation),
+            Strategy::Brent => None,
+        }
+    }
+
+    /// If has_repeat is true, this is how far the repeating pattern traveled over its period.
+    /// Anything that isn't stationary is a spaceship. When the repeat is only a rotated or flipped
+    /// copy, the offsets don't say how far the pattern really moved, so it's None then, see
+    /// find_translation.
+    pub fn velocity(&self) -> Option<Velocity> {
+        let repeat = self.repeat?;
+
+        if self.symmetry()? != Symmetry::Identity {
             return None;
-        };
+        }
 
-        let most_recent = self.grids_vec.last().unwrap();
+        let (now_i, now_j) = repeat.later.offset;
+        let (then_i, then_j) = repeat.earlier.offset;
 
-        // Go through our vector of states, starting from the most recent, and find how many back
-        // we have to go to get to the same one.
-        let period = self.grids_vec
-            .iter()
-            .rev()
-            .enumerate()
-            .position(|(index, grid)| index != 0 && grid == most_recent)
-            .expect(&format!("snapshot says it has a repeat but blew up getting the period, {:#?}", self.grids_vec));
+        Some(Velocity {
+            di: self.wrap_displacement(now_i as i64 - then_i as i64),
+            dj: self.wrap_displacement(now_j as i64 - then_j as i64),
+            period: self.period()?,
+        })
+    }
 
-        Some(period)
+    /// How many generations the board was run past the repeated state before the repeat was
+    /// confirmed. The full strategy only trusts a hash match once the board has come around the
+    /// loop a second time, so that's a whole period, while Brent's compares cells straight away.
+    pub fn confirmation_lag(&self) -> usize {
+        self.repeat
+            .map_or(0, |repeat| self.generation - 1 - repeat.later.generation)
+    }
+
+    /// If has_repeat is true, this is the rotation or reflection that takes the earlier state onto
+    /// the repeated one. Without symmetry turned on it's always the identity.
+    pub fn symmetry(&self) -> Option<Symmetry> {
+        let repeat = self.repeat?;
+
+        // Both states were turned into the same canonical one, so undoing the recent symmetry
+        // after doing the earlier one gets from the earlier state to the recent one.
+        Some(repeat.later.symmetry.inverse().then_after(repeat.earlier.symmetry))
+    }
+
+    /// The closest any state after the initial one has come to it, as the number of cells they
+    /// share over the number of cells in either, after both are shifted up against the origin.
+    /// 1 means the board got right back to where it started. None unless with_origin_similarity
+    /// was asked for, or before the second state.
+    pub fn origin_similarity(&self) -> Option<f64> {
+        self.origin_similarity
     }
 
     /// Commit the cells that were added to memory as a single grid state.
@@ -74,30 +339,428 @@ impl Snapshot {
     /// be called, so we do need to go through our own list one time here, but it's pre-sorted
     /// because it's in a binary tree so at least we don't have to sort it.
     pub fn commit_cells(&mut self) {
-        let serialized = self.serialize_cells();
-        self.grids_vec.push(serialized.clone()); // TODO It'd be way better if it lived in
-                                                 // grids_set and was referenced in here
-        let has_repeat = !self.grids_set.insert(serialized);
+        let canonical = self.canonicalize();
+        let hash = hash_cell_numbers(&canonical.cells);
 
-        if has_repeat {
-            self.has_repeat = true;
+        if self.track_origin {
+            match &self.origin {
+                None => self.origin = Some(canonical.cells.clone()),
+                Some(origin) => {
+                    let similarity = similarity(origin, &canonical.cells);
+                    self.origin_similarity = Some(
+                        self.origin_similarity
+                            .map_or(similarity, |best| best.max(similarity)),
+                    );
+                }
+            }
         }
 
+        let seen = Seen {
+            generation: self.generation,
+            offset: canonical.offset,
+            symmetry: canonical.symmetry,
+        };
+
+        match self.strategy {
+            Strategy::Full => self.commit_full(canonical, hash, seen),
+            Strategy::Brent => self.commit_brent(canonical, hash, seen),
+        }
+
+        self.generation += 1;
         self.current_iteration_cells.clear();
     }
 
-    /// We need to turn our cells into a single string so we can store them as a key into a
-    /// HashSet, which is what allows us to check and see if we have a repeat state in O(1)
-    /// time, which is critical b/c these boards can get into the thousands of iterations and we
-    /// can't have our check time growing with that number.
-    fn serialize_cells(&self) -> String {
-        let mut serialized = String::from("");
+    /// Check the newest state against the hash of every state we've seen so far
+    fn commit_full(&mut self, canonical: Canonical, hash: u128, seen: Seen) {
+        if let Some(candidate) = self.candidate.take() {
+            if candidate.confirm_at == self.generation {
+                // A real repeat means we've gone around the loop again and are right back on the
+                // same cells. Anything else was a collision, and we keep looking.
+                if candidate.cells == canonical.cells {
+                    self.repeat = Some(candidate.repeat);
+                }
+            } else {
+                self.candidate = Some(candidate);
+            }
+        }
+
+        if self.repeat.is_none() && self.candidate.is_none() {
+            if let Some(earlier) = self.seen.get(&hash) {
+                self.candidate = Some(Candidate {
+                    repeat: Repeat {
+                        earlier: *earlier,
+                        later: seen,
+                    },
+                    confirm_at: self.generation + (self.generation - earlier.generation),
+                    cells: canonical.cells,
+                });
+            }
+        }
+
+        self.seen.entry(hash).or_insert(seen);
+    }
+
+    /// Check the newest state against the tortoise. If the board goes a whole power of two
+    /// generations without matching it, the newest state becomes the tortoise and the power
+    /// doubles, so eventually the tortoise is inside the loop and the loop fits within the power.
+    fn commit_brent(&mut self, canonical: Canonical, hash: u128, seen: Seen) {
+        if self.repeat.is_some() {
+            return;
+        }
+
+        if let Some(tortoise) = &mut self.tortoise {
+            tortoise.lambda += 1;
 
-        self.current_iteration_cells.iter().for_each(|cell| {
-            serialized += &cell.to_string();
-            serialized += "|";
+            // We have the tortoise's cells on hand, so there's no need to wait to confirm
+            if tortoise.hash == hash && tortoise.cells == canonical.cells {
+                self.repeat = Some(Repeat {
+                    earlier: tortoise.seen,
+                    later: seen,
+                });
+                return;
+            }
+
+            if tortoise.lambda < tortoise.power {
+                return;
+            }
+        }
+
+        let power = self.tortoise.as_ref().map_or(1, |tortoise| tortoise.power * 2);
+
+        self.tortoise = Some(Tortoise {
+            seen,
+            hash,
+            cells: canonical.cells,
+            power,
+            lambda: 0,
         });
+    }
 
-        serialized
+    /// The top left corner of the smallest box containing every living cell. The cells are
+    /// ordered by row first, so the minimum row is just the first one.
+    fn bounding_box_origin(&self) -> (u32, u32) {
+        let min_i = self.current_iteration_cells.iter().next().map_or(0, |(i, _)| *i);
+        let min_j = self.current_iteration_cells.iter().map(|(_, j)| *j).min().unwrap_or(0);
+
+        (min_i, min_j)
+    }
+
+    /// The board wraps around (see Cells::neighbors), so a pattern that moved off one edge shows
+    /// up at the other. This brings a raw difference in position back to the shortest way around.
+    fn wrap_displacement(&self, delta: i64) -> i64 {
+        let width = self.width() as i64;
+        let delta = delta.rem_euclid(width);
+
+        if delta > width / 2 {
+            delta - width
+        } else {
+            delta
+        }
+    }
+
+    /// Cells live on coordinates 0..=size, so there's one more column than the size
+    fn width(&self) -> u32 {
+        self.size + 1
+    }
+
+    /// Turn the current cells into their cell numbers, shifted up against the origin, and if
+    /// we're ignoring symmetries, pick whichever of the 8 orientations comes out smallest so
+    /// every orientation of a state ends up the same.
+    fn canonicalize(&self) -> Canonical {
+        let offset = self.bounding_box_origin();
+
+        if !self.symmetric {
+            let (min_i, min_j) = offset;
+
+            // Already sorted, since the cells are ordered by row then column
+            let cells = self
+                .current_iteration_cells
+                .iter()
+                .map(|(i, j)| self.width() * (i - min_i) + (j - min_j))
+                .collect();
+
+            return Canonical {
+                cells,
+                offset,
+                symmetry: Symmetry::Identity,
+            };
+        }
+
+        Symmetry::ALL
+            .iter()
+            .map(|symmetry| {
+                let transformed: Vec<(i64, i64)> = self
+                    .current_iteration_cells
+                    .iter()
+                    .map(|(i, j)| symmetry.apply((*i as i64, *j as i64)))
+                    .collect();
+
+                let min_i = transformed.iter().map(|(i, _)| *i).min().unwrap_or(0);
+                let min_j = transformed.iter().map(|(_, j)| *j).min().unwrap_or(0);
+
+                let mut cells: Vec<u32> = transformed
+                    .iter()
+                    .map(|(i, j)| self.width() * (i - min_i) as u32 + (j - min_j) as u32)
+                    .collect();
+                cells.sort_unstable();
+
+                Canonical {
+                    cells,
+                    offset,
+                    symmetry: *symmetry,
+                }
+            })
+            // Ties go to the earliest symmetry, which keeps symmetric states on the identity
+            .min_by(|a, b| a.cells.cmp(&b.cells))
+            .unwrap()
+    }
+}
+
+/// Brent's algorithm (and anyone else who only knows the period) can find where the loop starts by
+/// running the board twice, with one copy a full period ahead of the other. The first generation
+/// at which both copies are in the same state is the first generation of the loop.
+///
+/// Ships taken off the board change where it goes, so both copies have to have them taken off the
+/// same way the original run did. Gives up with None after limit generations.
+pub fn find_cycle_start(
+    size: u32,
+    initial_cells: &[(u32, u32)],
+    period: usize,
+    symmetric: bool,
+    remove_emissions: bool,
+    limit: usize,
+) -> Option<usize> {
+    let new_game = || {
+        let mut cells = Cells::new(size);
+        cells.birth_multiple(initial_cells);
+        cells.commit();
+        let game = Game::new(None, cells, None, None);
+        let emissions = remove_emissions.then(|| EmissionDetector::new(true));
+        (game, emissions)
+    };
+
+    let step = |(game, emissions): &mut (Game, Option<EmissionDetector>)| {
+        game.step();
+        if let Some(emissions) = emissions {
+            let generation = game.generation();
+            emissions.inspect(&mut game.cells, generation);
+        }
+    };
+
+    let mut behind = new_game();
+    let mut ahead = new_game();
+
+    for _ in 0..period {
+        step(&mut ahead);
+    }
+
+    let canonical = |game: &Game| {
+        let mut snapshot = Snapshot::new(size);
+        snapshot.symmetric = symmetric;
+        game.cells
+            .living_cells()
+            .iter()
+            .for_each(|(i, j)| snapshot.add_cell(*i, *j));
+        snapshot.canonicalize().cells
+    };
+
+    for cycle_start in 0..=limit {
+        if canonical(&behind.0) == canonical(&ahead.0) {
+            return Some(cycle_start);
+        }
+        step(&mut behind);
+        step(&mut ahead);
+    }
+
+    None
+}
+
+/// A board that repeats as a rotation or reflection of itself only gets all the way around its loop
+/// after a few of those repeats. So starting from cells that are already in the loop, this runs
+/// the board without symmetry until it comes back exactly, for the full period and how far the
+/// pattern moves over it. The symmetric period is needed to know when to give up, since no
+/// symmetry takes more than 4 goes to get back to where it started.
+pub fn find_translation(
+    size: u32,
+    loop_cells: &[(u32, u32)],
+    symmetric_period: usize,
+) -> Option<Velocity> {
+    let mut cells = Cells::new(size);
+    cells.birth_multiple(loop_cells);
+    cells.commit();
+    let mut game = Game::new(Some(Snapshot::new(size)), cells, None, None);
+
+    // Up to 4 symmetric periods around the loop, and as long again to confirm it
+    for _ in 0..=symmetric_period * 8 {
+        game.step();
+
+        let snapshot = game.snapshot.as_ref()?;
+        if snapshot.has_repeat() {
+            return snapshot.velocity();
+        }
+    }
+
+    None
+}
+
+/// FNV-1a, widened to 128 bits so that with the number of states a board goes through,
+/// collisions are vanishingly rare. It's fed one cell number at a time, in order.
+fn hash_cell_numbers(cell_numbers: &[u32]) -> u128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+    cell_numbers
+        .iter()
+        .flat_map(|cell| cell.to_le_bytes())
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u128).wrapping_mul(PRIME)
+        })
+}
+
+/// How many cell numbers two sorted lists share, over how many are in either one
+fn similarity(a: &[u32], b: &[u32]) -> f64 {
+    let (mut x, mut y, mut shared) = (0, 0, 0);
+
+    while x < a.len() && y < b.len() {
+        match a[x].cmp(&b[y]) {
+            std::cmp::Ordering::Less => x += 1,
+            std::cmp::Ordering::Greater => y += 1,
+            std::cmp::Ordering::Equal => {
+                shared += 1;
+                x += 1;
+                y += 1;
+            }
+        }
+    }
+
+    let either = a.len() + b.len() - shared;
+
+    if either == 0 {
+        0.0
+    } else {
+        shared as f64 / either as f64
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[test]
+fn detects_glider_as_spaceship() {
+    let mut cells = crate::Cells::new(50);
+    cells.birth_multiple(&[(10, 11), (11, 12), (12, 10), (12, 11), (12, 12)]);
+    let mut game = crate::Game::new(Some(Snapshot::new(50)), cells, None, None);
+
+    while !game.snapshot.as_ref().unwrap().has_repeat() {
+        game.step();
+    }
+
+    let snapshot = game.snapshot.unwrap();
+    let velocity = snapshot.velocity().unwrap();
+
+    assert_eq!(snapshot.period(), Some(4));
+    assert_eq!((velocity.di, velocity.dj), (1, 1));
+    assert_eq!(velocity.to_string(), "c/4 diagonal");
+}
+
+#[test]
+fn tracks_similarity_to_the_initial_state() {
+    // A glider comes right back to itself, an R-pentomino never does
+    let glider = [(10, 11), (11, 12), (12, 10), (12, 11), (12, 12)];
+    let r_pentomino = [(20, 21), (20, 22), (21, 20), (21, 21), (22, 21)];
+
+    for (initial, returns) in [(&glider, true), (&r_pentomino, false)] {
+        let mut cells = Cells::new(50);
+        cells.birth_multiple(initial);
+        let snapshot = Snapshot::new(50).with_origin_similarity();
+        let mut game = Game::new(Some(snapshot), cells, None, None);
+
+        for _ in 0..8 {
+            game.step();
+        }
+
+        let similarity = game.snapshot.unwrap().origin_similarity().unwrap();
+        assert_eq!(similarity == 1.0, returns);
+        assert!(similarity > 0.0);
+    }
+}
+
+#[test]
+fn detects_glider_at_half_period_with_symmetry() {
+    let mut cells = crate::Cells::new(50);
+    cells.birth_multiple(&[(10, 11), (11, 12), (12, 10), (12, 11), (12, 12)]);
+    let snapshot = Snapshot::new(50).with_symmetry();
+    let mut game = crate::Game::new(Some(snapshot), cells, None, None);
+
+    while !game.snapshot.as_ref().unwrap().has_repeat() {
+        game.step();
+    }
+
+    let snapshot = game.snapshot.unwrap();
+
+    // Two generations on, the glider is a reflection of itself
+    assert_eq!(snapshot.period(), Some(2));
+    assert_ne!(snapshot.symmetry(), Some(Symmetry::Identity));
+
+    // But it takes the full 4 to move, and the reflected offsets can't say how far
+    assert_eq!(snapshot.velocity(), None);
+    let velocity = find_translation(50, &game.cells.living_cells(), 2).unwrap();
+    assert_eq!(velocity.period, 4);
+    assert_eq!(velocity.to_string(), "c/4 diagonal");
+}
+
+#[test]
+fn brent_finds_period_and_cycle_start() {
+    // Three cells in an L fill in to a block after one generation
+    let initial = [(10, 10), (10, 11), (11, 10)];
+
+    for strategy in [Strategy::Full, Strategy::Brent] {
+        let mut cells = Cells::new(50);
+        cells.birth_multiple(&initial);
+        let snapshot = Snapshot::new(50).with_strategy(strategy);
+        let mut game = Game::new(Some(snapshot), cells, None, None);
+
+        while !game.snapshot.as_ref().unwrap().has_repeat() {
+            game.step();
+        }
+
+        let snapshot = game.snapshot.unwrap();
+        assert_eq!(snapshot.period(), Some(1));
+
+        // The full strategy waits out one more period to make sure the hash wasn't a collision
+        let lag = match strategy {
+            Strategy::Full => 1,
+            Strategy::Brent => 0,
+        };
+        assert_eq!(snapshot.confirmation_lag(), lag);
     }
+
+    assert_eq!(find_cycle_start(50, &initial, 1, false, false, 10), Some(1));
+}
+
+#[test]
+fn finds_cycle_start_with_ships_taken_off() {
+    // A block, and a glider heading away from it
+    let initial = [
+        (10, 10),
+        (10, 11),
+        (11, 10),
+        (11, 11),
+        (20, 21),
+        (21, 22),
+        (22, 20),
+        (22, 21),
+        (22, 22),
+    ];
+
+    // Left alone, the glider's still on its way for as long as we look
+    assert_eq!(find_cycle_start(60, &initial, 1, false, false, 100), None);
+
+    // Taken off as soon as it's found, the block's all that's left
+    assert_eq!(find_cycle_start(60, &initial, 1, false, true, 100), Some(16));
 }
@@ -0,0 +1,147 @@
+use std::fmt;
+
+use crate::cells::BoundingBox;
+use crate::Cells;
+
+// The stats' responsibility is to keep a time series of how a board changes over a run, one entry
+// per generation, so runs can be plotted and compared. It's kept separate from the snapshot since
+// it's optional and, unlike the snapshot, holds on to something for every generation.
+
+/// What a single generation of a board looked like
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Generation {
+    /// 0 is the board as it was set up
+    pub generation: usize,
+
+    pub population: usize,
+
+    /// Cells that came alive going into this generation
+    pub births: usize,
+
+    /// Cells that died going into this generation
+    pub deaths: usize,
+
+    /// The box around every living cell, by board coordinates. None if nothing's alive.
+    pub bounding_box: Option<BoundingBox>,
+
+    /// The average age of the living cells, 0 if nothing's alive
+    pub mean_age: f64,
+}
+
+/// Every generation of a run, in order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub generations: Vec<Generation>,
+}
+
+impl Stats {
+    pub fn new() -> Stats {
+        Stats::default()
+    }
+
+    /// Add an entry for the cells as they are now. Births are read off of the cells' ages, since
+    /// any cell of age 1 was just born, and everything else that's missing since the last
+    /// generation must have died.
+    pub fn record(&mut self, cells: &Cells, generation: usize) {
+        let living = cells.living_cells();
+        let population = living.len();
+
+        let ages: Vec<usize> = living.iter().map(|(i, j)| cells.get_age(*i, *j)).collect();
+
+        let (births, deaths) = match self.generations.last() {
+            Some(previous) => {
+                let births = ages.iter().filter(|age| **age == 1).count();
+                let survivors = population - births;
+                (births, previous.population - survivors)
+            }
+
+            // The initial cells were put there, not born
+            None => (0, 0),
+        };
+
+        let bounding_box = BoundingBox::around(living.iter());
+
+        let mean_age = if population == 0 {
+            0.0
+        } else {
+            ages.iter().sum::<usize>() as f64 / population as f64
+        };
+
+        self.generations.push(Generation {
+            generation,
+            population,
+            births,
+            deaths,
+            bounding_box,
+            mean_age,
+        });
+    }
+
+    /// The most living cells at any one time
+    pub fn peak_population(&self) -> usize {
+        self.generations
+            .iter()
+            .map(|generation| generation.population)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn populations(&self) -> Vec<usize> {
+        self.generations
+            .iter()
+            .map(|generation| generation.population)
+            .collect()
+    }
+}
+
+/// CSV, with a header, for plotting
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "generation,population,births,deaths,top,left,height,width,mean_age")?;
+
+        for generation in &self.generations {
+            let bounding_box = match generation.bounding_box {
+                Some(b) => format!("{},{},{},{}", b.top, b.left, b.height, b.width),
+                None => String::from(",,,"),
+            };
+
+            writeln!(
+                f,
+                "{},{},{},{},{},{:.3}",
+                generation.generation,
+                generation.population,
+                generation.births,
+                generation.deaths,
+                bounding_box,
+                generation.mean_age
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn counts_a_blinkers_births_and_deaths() {
+    let mut cells = Cells::new(20);
+    cells.birth_multiple(&[(10, 9), (10, 10), (10, 11)]);
+    let mut game = crate::Game::new(None, cells, None, Some(Stats::new()));
+
+    // The first step only loads the cells in, as generation 0
+    for _ in 0..4 {
+        game.step();
+    }
+
+    let generations = game.stats.unwrap().generations;
+    let counts: Vec<(usize, usize, usize, usize)> = generations
+        .iter()
+        .map(|g| (g.generation, g.population, g.births, g.deaths))
+        .collect();
+
+    // Every generation, the two ends die and two new ones are born across the middle
+    assert_eq!(counts, vec![(0, 3, 0, 0), (1, 3, 2, 2), (2, 3, 2, 2), (3, 3, 2, 2)]);
+    assert_eq!(
+        generations[1].bounding_box,
+        Some(BoundingBox { top: 9, left: 10, height: 3, width: 1 })
+    );
+}